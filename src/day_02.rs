@@ -8,11 +8,53 @@ use nom::{
     sequence::{preceded, tuple},
     IResult, Parser,
 };
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt};
 
 type MapTo = Vec<Vec<(u32, String)>>;
 type Line = (u32, MapTo);
-type Input = HashMap<u32, MapTo>;
+type Input = Vec<Line>;
+
+#[derive(Debug)]
+pub enum GameIdError {
+    Duplicate {
+        id: u32,
+        line: usize,
+        first_line: usize,
+    },
+    /// `after` is the previous game's id, or `None` if the first game isn't Game 1.
+    Gap {
+        after: Option<u32>,
+        found: u32,
+        line: usize,
+    },
+}
+
+impl fmt::Display for GameIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameIdError::Duplicate {
+                id,
+                line,
+                first_line,
+            } => write!(
+                f,
+                "line {line}: duplicate Game {id} (first seen on line {first_line})"
+            ),
+            GameIdError::Gap {
+                after: Some(after),
+                found,
+                line,
+            } => write!(f, "line {line}: Game {found} does not follow Game {after}"),
+            GameIdError::Gap {
+                after: None,
+                found,
+                line,
+            } => write!(f, "line {line}: expected Game 1, found Game {found}"),
+        }
+    }
+}
+
+impl std::error::Error for GameIdError {}
 
 fn parse_line(input: &str) -> IResult<&str, Line> {
     tuple((
@@ -38,21 +80,66 @@ fn parse_line(input: &str) -> IResult<&str, Line> {
 }
 
 fn parse_input(input: &str) -> IResult<&str, Input> {
-    let (input, list) = separated_list1(line_ending, parse_line)(input)?;
+    separated_list1(line_ending, parse_line)(input)
+}
+
+/// Games are one per line, so a game's position doubles as its (1-based) line number.
+/// With `contiguous` set, ids must also count up from 1 with nothing skipped.
+fn validate_ids(games: &[Line], contiguous: bool) -> Result<(), GameIdError> {
+    let mut seen = HashMap::new();
+    let mut previous: Option<u32> = None;
 
-    Ok((input, list.into_iter().collect()))
+    for (index, (id, _)) in games.iter().enumerate() {
+        let line = index + 1;
+        if let Some(&first_line) = seen.get(id) {
+            return Err(GameIdError::Duplicate {
+                id: *id,
+                line,
+                first_line,
+            });
+        }
+        seen.insert(*id, line);
+
+        let expected = previous.map_or(Some(1), |previous| previous.checked_add(1));
+        if contiguous && expected != Some(*id) {
+            return Err(GameIdError::Gap {
+                after: previous,
+                found: *id,
+                line,
+            });
+        }
+        previous = Some(*id);
+    }
+
+    Ok(())
 }
 
 #[aoc_generator(day2)]
-pub fn day2_generator(input: &str) -> Input {
+pub fn day2_generator(input: &str) -> Result<Input, GameIdError> {
     let _input = "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
 Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
 Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
 Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
 Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green";
+    generate(input, true)
+}
+
+/// Accepts ids in any order and with gaps, as long as none repeats.
+#[aoc_generator(day2, part1, Sparse)]
+pub fn day2_sparse_generator(input: &str) -> Result<Input, GameIdError> {
+    generate(input, false)
+}
+
+#[aoc_generator(day2, part2, Sparse)]
+pub fn day2_sparse_part2_generator(input: &str) -> Result<Input, GameIdError> {
+    generate(input, false)
+}
+
+fn generate(input: &str, contiguous: bool) -> Result<Input, GameIdError> {
     let (input, output) = parse_input(input).unwrap();
     assert!(input.is_empty());
-    output
+    validate_ids(&output, contiguous)?;
+    Ok(output)
 }
 
 #[aoc(day2, part1)]
@@ -79,8 +166,8 @@ pub fn solve_part1(input: &Input) -> u32 {
 #[aoc(day2, part2)]
 pub fn solve_part2(input: &Input) -> u32 {
     input
-        .values()
-        .map(|v| {
+        .iter()
+        .map(|(_, v)| {
             let (max_green, max_red, max_blue) = v.iter().fold((0, 0, 0), |mut acc, pull| {
                 pull.iter()
                     .for_each(|(number, color)| match color.as_str() {
@@ -107,3 +194,68 @@ pub fn solve_part2(input: &Input) -> u32 {
         })
         .sum()
 }
+
+#[aoc(day2, part1, Sparse)]
+pub fn solve_part1_sparse(input: &Input) -> u32 {
+    solve_part1(input)
+}
+
+#[aoc(day2, part2, Sparse)]
+pub fn solve_part2_sparse(input: &Input) -> u32 {
+    solve_part2(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn games(ids: &[u32]) -> String {
+        ids.iter()
+            .map(|id| format!("Game {id}: 1 red"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn contiguous_ids_start_at_one() {
+        assert!(day2_generator(&games(&[1, 2, 3])).is_ok());
+        assert!(matches!(
+            day2_generator(&games(&[7, 8])),
+            Err(GameIdError::Gap {
+                after: None,
+                found: 7,
+                line: 1
+            })
+        ));
+        assert!(matches!(
+            day2_generator(&games(&[1, 3])),
+            Err(GameIdError::Gap {
+                after: Some(1),
+                found: 3,
+                line: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn sparse_ids_only_reject_duplicates() {
+        assert!(day2_sparse_generator(&games(&[7, 3, 12])).is_ok());
+        assert!(matches!(
+            day2_sparse_generator(&games(&[7, 3, 7])),
+            Err(GameIdError::Duplicate {
+                id: 7,
+                line: 3,
+                first_line: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn max_id_does_not_overflow() {
+        assert!(day2_sparse_generator(&games(&[u32::MAX, 1])).is_ok());
+        assert!(matches!(
+            day2_generator(&games(&[u32::MAX])),
+            Err(GameIdError::Gap { after: None, .. })
+        ));
+    }
+}
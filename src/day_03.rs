@@ -1,5 +1,7 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 
+use colored::Colorize;
+use itertools::Itertools;
 use nom::{
     branch::alt,
     bytes::complete::tag,
@@ -7,34 +9,317 @@ use nom::{
     multi::{many0, separated_list1},
     IResult, Parser,
 };
+use std::fmt;
 
 #[derive(Debug, Copy, Clone)]
 pub enum Chars {
     Blank,
-    Symbol,
+    Symbol(char),
     Number(u32),
-    Gear,
 }
 
-type Input = Vec<Vec<Chars>>;
+#[derive(Debug)]
+struct PartNumber {
+    row: usize,
+    start: usize,
+    end: usize,
+    value: u32,
+}
+
+#[derive(Debug)]
+struct Symbol {
+    row: usize,
+    column: usize,
+    glyph: char,
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Render {
+    Color,
+    Plain,
+}
+
+#[derive(Debug)]
+pub enum SchematicError {
+    /// A run of digits that doesn't fit in a `u32`, at its 1-based line and column.
+    NumberTooLarge {
+        line: usize,
+        column: usize,
+    },
+    /// The ratio of the symbol at this 1-based line and column doesn't fit in a `u64`.
+    RatioTooLarge {
+        line: usize,
+        column: usize,
+    },
+    SumOverflow,
+}
+
+impl fmt::Display for SchematicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchematicError::NumberTooLarge { line, column } => {
+                write!(f, "line {line}, column {column}: number is too large")
+            }
+            SchematicError::RatioTooLarge { line, column } => {
+                write!(f, "line {line}, column {column}: ratio is too large")
+            }
+            SchematicError::SumOverflow => write!(f, "sum is too large"),
+        }
+    }
+}
+
+impl std::error::Error for SchematicError {}
+
+/// Every number span and symbol in the grid, plus which of them touch.
+/// `number_symbols[n]` lists the symbols around `numbers[n]`, and
+/// `symbol_numbers[s]` lists the numbers around `symbols[s]`.
+#[derive(Debug)]
+pub struct Schematic {
+    width: usize,
+    height: usize,
+    numbers: Vec<PartNumber>,
+    symbols: Vec<Symbol>,
+    number_symbols: Vec<Vec<usize>>,
+    symbol_numbers: Vec<Vec<usize>>,
+}
+
+impl Schematic {
+    fn new(grid: &[Vec<Chars>]) -> Result<Self, SchematicError> {
+        let mut numbers = vec![];
+        let mut symbols = vec![];
+        let mut symbol_at = vec![];
+
+        for (row, line) in grid.iter().enumerate() {
+            let mut symbol_row = vec![None; line.len()];
+            let mut current: Option<PartNumber> = None;
+
+            for (column, c) in line.iter().enumerate() {
+                match c {
+                    Chars::Number(n) => match current.as_mut() {
+                        Some(number) => {
+                            number.end = column;
+                            number.value = number
+                                .value
+                                .checked_mul(10)
+                                .and_then(|value| value.checked_add(*n))
+                                .ok_or(SchematicError::NumberTooLarge {
+                                    line: row + 1,
+                                    column: number.start + 1,
+                                })?;
+                        }
+                        None => {
+                            current = Some(PartNumber {
+                                row,
+                                start: column,
+                                end: column,
+                                value: *n,
+                            })
+                        }
+                    },
+                    _ => {
+                        numbers.extend(current.take());
+                        if let Chars::Symbol(glyph) = c {
+                            symbol_row[column] = Some(symbols.len());
+                            symbols.push(Symbol {
+                                row,
+                                column,
+                                glyph: *glyph,
+                            });
+                        }
+                    }
+                }
+            }
+
+            numbers.extend(current);
+            symbol_at.push(symbol_row);
+        }
+
+        let mut number_symbols = vec![vec![]; numbers.len()];
+        let mut symbol_numbers = vec![vec![]; symbols.len()];
+
+        for (n, number) in numbers.iter().enumerate() {
+            for row in number.row.saturating_sub(1)..=number.row + 1 {
+                let Some(symbol_row) = symbol_at.get(row) else {
+                    continue;
+                };
+                for column in number.start.saturating_sub(1)..=number.end + 1 {
+                    if let Some(&Some(s)) = symbol_row.get(column) {
+                        number_symbols[n].push(s);
+                        symbol_numbers[s].push(n);
+                    }
+                }
+            }
+        }
+
+        Ok(Schematic {
+            width: grid.iter().map(Vec::len).max().unwrap_or_default(),
+            height: grid.len(),
+            numbers,
+            symbols,
+            number_symbols,
+            symbol_numbers,
+        })
+    }
+
+    /// Numbers touching at least one symbol accepted by `is_symbol`, each counted once.
+    fn part_numbers<'a>(
+        &'a self,
+        is_symbol: impl Fn(char) -> bool + 'a,
+    ) -> impl Iterator<Item = &'a PartNumber> + 'a {
+        self.numbers
+            .iter()
+            .zip(self.number_symbols.iter())
+            .filter(move |(_, symbols)| symbols.iter().any(|&s| is_symbol(self.symbols[s].glyph)))
+            .map(|(number, _)| number)
+    }
+
+    fn sum_adjacent(&self, is_symbol: impl Fn(char) -> bool) -> u64 {
+        self.part_numbers(is_symbol)
+            .map(|number| number.value as u64)
+            .sum()
+    }
+
+    /// Symbols (optionally only `glyph`) with exactly `arity` neighbouring numbers,
+    /// alongside those numbers.
+    fn symbols_with_arity(
+        &self,
+        glyph: Option<char>,
+        arity: usize,
+    ) -> impl Iterator<Item = (&Symbol, &[usize])> {
+        self.symbols
+            .iter()
+            .zip(self.symbol_numbers.iter())
+            .filter(move |(symbol, numbers)| {
+                glyph.is_none_or(|g| symbol.glyph == g) && numbers.len() == arity
+            })
+            .map(|(symbol, numbers)| (symbol, numbers.as_slice()))
+    }
+
+    /// Product of the numbers around `symbol`.
+    fn ratio(&self, symbol: &Symbol, numbers: &[usize]) -> Result<u64, SchematicError> {
+        numbers
+            .iter()
+            .try_fold(1_u64, |ratio, &n| {
+                ratio.checked_mul(self.numbers[n].value as u64)
+            })
+            .ok_or(SchematicError::RatioTooLarge {
+                line: symbol.row + 1,
+                column: symbol.column + 1,
+            })
+    }
+
+    /// Ratio of every `glyph` with exactly `arity` neighbouring numbers.
+    fn gear_ratios(
+        &self,
+        glyph: char,
+        arity: usize,
+    ) -> impl Iterator<Item = Result<u64, SchematicError>> + '_ {
+        self.symbols_with_arity(Some(glyph), arity)
+            .map(|(symbol, numbers)| self.ratio(symbol, numbers))
+    }
+
+    fn sum_ratios(&self, glyph: char, arity: usize) -> Result<u64, SchematicError> {
+        self.gear_ratios(glyph, arity)
+            .try_fold(0_u64, |total, ratio| {
+                total.checked_add(ratio?).ok_or(SchematicError::SumOverflow)
+            })
+    }
+
+    /// Reprints the grid with part numbers highlighted, other numbers dimmed and
+    /// two-number gears marked, followed by every gear's ratio. `Render::Plain`
+    /// marks part numbers as `[467]` and gears as `<*>` instead of colouring them.
+    fn render(&self, mode: Render) -> String {
+        let mut out = String::new();
+        let mut numbers = self.numbers.iter().enumerate().peekable();
+        let mut symbols = self.symbols.iter().enumerate().peekable();
+
+        for row in 0..self.height {
+            let mut column = 0;
+            while column < self.width {
+                if let Some((n, number)) =
+                    numbers.next_if(|(_, number)| number.row == row && number.start == column)
+                {
+                    let digits = format!(
+                        "{:0width$}",
+                        number.value,
+                        width = number.end - number.start + 1
+                    );
+                    let counted = !self.number_symbols[n].is_empty();
+                    match (mode, counted) {
+                        (Render::Color, true) => out += &digits.green().bold().to_string(),
+                        (Render::Color, false) => out += &digits.dimmed().to_string(),
+                        (Render::Plain, true) => out += &format!("[{digits}]"),
+                        (Render::Plain, false) => out += &digits,
+                    }
+                    column = number.end + 1;
+                } else if let Some((s, symbol)) =
+                    symbols.next_if(|(_, symbol)| symbol.row == row && symbol.column == column)
+                {
+                    let glyph = symbol.glyph.to_string();
+                    let gear = symbol.glyph == '*' && self.symbol_numbers[s].len() == 2;
+                    match (mode, gear) {
+                        (Render::Color, true) => out += &glyph.yellow().bold().to_string(),
+                        (Render::Plain, true) => out += &format!("<{glyph}>"),
+                        (_, false) => out += &glyph,
+                    }
+                    column += 1;
+                } else {
+                    out.push('.');
+                    column += 1;
+                }
+            }
+            out.push('\n');
+        }
+
+        for (symbol, numbers) in self.symbols_with_arity(Some('*'), 2) {
+            let ratio = self
+                .ratio(symbol, numbers)
+                .map_or_else(|e| e.to_string(), |ratio| ratio.to_string());
+            out += &format!(
+                "\ngear at line {}, column {}: {} = {ratio}",
+                symbol.row + 1,
+                symbol.column + 1,
+                numbers.iter().map(|&n| self.numbers[n].value).join(" * "),
+            );
+        }
+
+        out
+    }
+
+    fn print(&self) {
+        let mode = if colored::control::SHOULD_COLORIZE.should_colorize() {
+            Render::Color
+        } else {
+            Render::Plain
+        };
+        println!("{}", self.render(mode));
+    }
+}
+
+type Input = Schematic;
 
 fn parse_line(input: &str) -> IResult<&str, Vec<Chars>> {
     many0(alt((
         tag(".").map(|_| Chars::Blank),
-        tag("*").map(|_| Chars::Gear),
         one_of("0123456789").map(|n| Chars::Number(n.to_digit(10).expect("Digit"))),
-        none_of(".0123456789*\n").map(|_| Chars::Symbol),
+        none_of(".0123456789\r\n").map(Chars::Symbol),
     )))(input)
 }
 
-fn parse_input(input: &str) -> IResult<&str, Input> {
-    let (input, list) = separated_list1(line_ending, parse_line)(input)?;
+/// Short rows are padded with blanks up to the widest row, so every cell
+/// around a number or symbol exists regardless of how ragged the input was.
+fn parse_input(input: &str) -> IResult<&str, Vec<Vec<Chars>>> {
+    let (input, mut grid) = separated_list1(line_ending, parse_line)(input)?;
 
-    Ok((input, list.into_iter().collect()))
+    let width = grid.iter().map(Vec::len).max().unwrap_or_default();
+    grid.iter_mut()
+        .for_each(|row| row.resize(width, Chars::Blank));
+
+    Ok((input, grid))
 }
 
 #[aoc_generator(day3)]
-pub fn day2_generator(input: &str) -> Input {
+pub fn day2_generator(input: &str) -> Result<Input, SchematicError> {
     let _input = "467..114..
 ...*......
 ..35..633.
@@ -46,306 +331,189 @@ pub fn day2_generator(input: &str) -> Input {
 ...$.*....
 .664.598..";
 
-    let (input, output) = parse_input(input).expect("Could not parse input");
+    let (input, grid) = parse_input(input).expect("Could not parse input");
     assert!(input.is_empty());
-    output
+    Schematic::new(&grid)
 }
 
 #[aoc(day3, part1)]
-pub fn solve_part1(input: &Input) -> u32 {
-    input
-        .iter()
-        .enumerate()
-        .map(|(level, row)| {
-            let (num, sum) = row
-                .iter()
-                .enumerate()
-                .filter(|(column, n)| {
-                    // check if n should be included still
-                    match n {
-                        Chars::Number(_) => {
-                            let mut left_boundary = (0..=*column)
-                                .rev()
-                                .take_while(|i| matches!(input[level][*i], Chars::Number(_)))
-                                .min()
-                                .expect("Left boundary");
-                            let mut right_boundary = (*column..input[level].len())
-                                .take_while(|i| matches!(input[level][*i], Chars::Number(_)))
-                                .max()
-                                .expect("Right boundary");
-
-                            left_boundary = left_boundary.saturating_sub(1);
-                            if right_boundary < input[level].len() - 1 {
-                                right_boundary += 1;
-                            }
-
-                            if level > 0
-                                && (left_boundary..=right_boundary).any(|index| {
-                                    matches!(input[level - 1][index], Chars::Gear | Chars::Symbol)
-                                })
-                            {
-                                return true;
-                            }
-                            if level < input.len() - 1
-                                && (left_boundary..=right_boundary).any(|index| {
-                                    matches!(input[level + 1][index], Chars::Gear | Chars::Symbol)
-                                })
-                            {
-                                return true;
-                            }
-                            if matches!(input[level][left_boundary], Chars::Gear | Chars::Symbol) {
-                                return true;
-                            }
-                            if matches!(input[level][right_boundary], Chars::Gear | Chars::Symbol) {
-                                return true;
-                            }
-
-                            false
-                        }
-                        _ => true,
-                    }
-                })
-                .fold((0, 0), |acc, (_, c)| match c {
-                    Chars::Number(n) => (acc.0 * 10 + n, acc.1),
-                    _ => (0, acc.1 + acc.0),
-                });
-            num + sum
-        })
-        .sum::<u32>()
+pub fn solve_part1(input: &Input) -> u64 {
+    input.sum_adjacent(|_| true)
 }
 
 #[aoc(day3, part2)]
-pub fn solve_part2(input: &Input) -> u32 {
-    input
-        .iter()
-        .enumerate()
-        .map(|(level, row)| {
-            row.iter()
-                .enumerate()
-                .map(|(column, n)| {
-                    // check if n should be included still
-                    match n {
-                        Chars::Gear => {
-                            let mut found_up = false;
-                            let mut up_num = 1;
-                            let mut found_down = false;
-                            let mut down_num = 1;
-                            let mut found_left = false;
-                            let mut left_num = 1;
-                            let mut found_right = false;
-                            let mut right_num = 1;
-
-                            let mut found_upleft = false;
-                            let mut upleft_num = 1;
-                            let mut found_upright = false;
-                            let mut upright_num = 1;
-
-                            let mut found_downleft = false;
-                            let mut downleft_num = 1;
-                            let mut found_downright = false;
-                            let mut downright_num = 1;
-
-                            if level > 0 {
-                                found_up = matches!(input[level - 1][column], Chars::Number(_));
-                                if found_up {
-                                    let left_boundary = (0..=column)
-                                        .rev()
-                                        .take_while(|i| {
-                                            matches!(input[level - 1][*i], Chars::Number(_))
-                                        })
-                                        .min()
-                                        .expect("Left boundary");
-                                    let right_boundary = (column..input[level].len())
-                                        .take_while(|i| {
-                                            matches!(input[level - 1][*i], Chars::Number(_))
-                                        })
-                                        .max()
-                                        .expect("Right boundary");
-
-                                    up_num = (left_boundary..=right_boundary)
-                                        .map(|i| match input[level - 1][i] {
-                                            Chars::Number(n) => n,
-                                            _ => unreachable!(),
-                                        })
-                                        .fold(0, |acc, n| acc * 10 + n);
-                                }
-                                if !found_up
-                                    && column > 0
-                                    && matches!(input[level - 1][column - 1], Chars::Number(_))
-                                {
-                                    found_upleft = true;
-                                    let left_boundary = (0..column)
-                                        .rev()
-                                        .take_while(|i| {
-                                            matches!(input[level - 1][*i], Chars::Number(_))
-                                        })
-                                        .min()
-                                        .expect("Left boundary");
-                                    let right_boundary = column - 1;
-
-                                    upleft_num = (left_boundary..=right_boundary)
-                                        .map(|i| match input[level - 1][i] {
-                                            Chars::Number(n) => n,
-                                            _ => unreachable!(),
-                                        })
-                                        .fold(0, |acc, n| acc * 10 + n);
-                                }
-                                if !found_up
-                                    && column < input[level].len() - 1
-                                    && matches!(input[level - 1][column + 1], Chars::Number(_))
-                                {
-                                    found_upright = true;
-                                    let left_boundary = column + 1;
-                                    let right_boundary = (column + 1..input[level].len())
-                                        .take_while(|i| {
-                                            matches!(input[level - 1][*i], Chars::Number(_))
-                                        })
-                                        .max()
-                                        .expect("Right boundary");
-
-                                    upright_num = (left_boundary..=right_boundary)
-                                        .map(|i| match input[level - 1][i] {
-                                            Chars::Number(n) => n,
-                                            _ => unreachable!(),
-                                        })
-                                        .fold(0, |acc, n| acc * 10 + n);
-                                }
-                            }
-
-                            if level < input.len() - 1 {
-                                found_down = matches!(input[level + 1][column], Chars::Number(_));
-                                if found_down {
-                                    let left_boundary = (0..=column)
-                                        .rev()
-                                        .take_while(|i| {
-                                            matches!(input[level + 1][*i], Chars::Number(_))
-                                        })
-                                        .min()
-                                        .expect("Left boundary");
-                                    let right_boundary = (column..input[level].len())
-                                        .take_while(|i| {
-                                            matches!(input[level + 1][*i], Chars::Number(_))
-                                        })
-                                        .max()
-                                        .expect("Right boundary");
-
-                                    down_num = (left_boundary..=right_boundary)
-                                        .map(|i| match input[level + 1][i] {
-                                            Chars::Number(n) => n,
-                                            _ => unreachable!(),
-                                        })
-                                        .fold(0, |acc, n| acc * 10 + n);
-                                }
-                                if !found_down
-                                    && column > 0
-                                    && matches!(input[level + 1][column - 1], Chars::Number(_))
-                                {
-                                    found_downleft = true;
-                                    let left_boundary = (0..column)
-                                        .rev()
-                                        .take_while(|i| {
-                                            matches!(input[level + 1][*i], Chars::Number(_))
-                                        })
-                                        .min()
-                                        .expect("Left boundary");
-                                    let right_boundary = column - 1;
-
-                                    downleft_num = (left_boundary..=right_boundary)
-                                        .map(|i| match input[level + 1][i] {
-                                            Chars::Number(n) => n,
-                                            _ => unreachable!(),
-                                        })
-                                        .fold(0, |acc, n| acc * 10 + n);
-                                }
-                                if !found_down
-                                    && column < input[level].len() - 1
-                                    && matches!(input[level + 1][column + 1], Chars::Number(_))
-                                {
-                                    found_downright = true;
-                                    let left_boundary = column + 1;
-                                    let right_boundary = (column + 1..input[level].len())
-                                        .take_while(|i| {
-                                            matches!(input[level + 1][*i], Chars::Number(_))
-                                        })
-                                        .max()
-                                        .expect("Right boundary");
-
-                                    downright_num = (left_boundary..=right_boundary)
-                                        .map(|i| match input[level + 1][i] {
-                                            Chars::Number(n) => n,
-                                            _ => unreachable!(),
-                                        })
-                                        .fold(0, |acc, n| acc * 10 + n);
-                                }
-                            }
-
-                            if column > 0 && matches!(input[level][column - 1], Chars::Number(_)) {
-                                found_left = true;
-                                let left_boundary = (0..column)
-                                    .rev()
-                                    .take_while(|i| matches!(input[level][*i], Chars::Number(_)))
-                                    .min()
-                                    .expect("Left boundary");
-                                let right_boundary = column - 1;
-
-                                left_num = (left_boundary..=right_boundary)
-                                    .map(|i| match input[level][i] {
-                                        Chars::Number(n) => n,
-                                        _ => unreachable!(),
-                                    })
-                                    .fold(0, |acc, n| acc * 10 + n);
-                            }
-
-                            if column < input[level].len() - 1
-                                && matches!(input[level][column + 1], Chars::Number(_))
-                            {
-                                found_right = true;
-                                let left_boundary = column + 1;
-                                let right_boundary = (column + 1..input[level].len())
-                                    .take_while(|i| matches!(input[level][*i], Chars::Number(_)))
-                                    .max()
-                                    .expect("Right boundary");
-
-                                right_num = (left_boundary..=right_boundary)
-                                    .map(|i| match input[level][i] {
-                                        Chars::Number(n) => n,
-                                        _ => unreachable!(),
-                                    })
-                                    .fold(0, |acc, n| acc * 10 + n);
-                            }
-
-                            if [
-                                found_up,
-                                found_down,
-                                found_left,
-                                found_right,
-                                found_upleft,
-                                found_upright,
-                                found_downleft,
-                                found_downright,
-                            ]
-                            .iter()
-                            .filter(|i| **i)
-                            .count()
-                                == 2
-                            {
-                                up_num
-                                    * down_num
-                                    * left_num
-                                    * right_num
-                                    * upleft_num
-                                    * upright_num
-                                    * downleft_num
-                                    * downright_num
-                            } else {
-                                0
-                            }
-                        }
-                        Chars::Symbol => 0,
-                        _ => 0,
-                    }
-                })
-                .sum::<u32>()
-        })
-        .sum::<u32>()
+pub fn solve_part2(input: &Input) -> Result<u64, SchematicError> {
+    input.sum_ratios('*', 2)
+}
+
+#[aoc(day3, part1, Render)]
+pub fn render_part1(input: &Input) -> u64 {
+    input.print();
+    solve_part1(input)
+}
+
+#[aoc(day3, part2, Render)]
+pub fn render_part2(input: &Input) -> Result<u64, SchematicError> {
+    input.print();
+    solve_part2(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "467..114..
+...*......
+..35..633.
+......#...
+617*......
+.....+.58.
+..592.....
+......755.
+...$.*....
+.664.598..";
+
+    fn solve(input: &str) -> (u64, u64) {
+        let schematic = day2_generator(input).expect("valid schematic");
+        (
+            solve_part1(&schematic),
+            solve_part2(&schematic).expect("ratios to fit"),
+        )
+    }
+
+    #[test]
+    fn example() {
+        assert_eq!(solve(EXAMPLE), (4361, 467835));
+    }
+
+    #[test]
+    fn symbol_specific_queries() {
+        let schematic = day2_generator(EXAMPLE).unwrap();
+        assert_eq!(schematic.sum_adjacent(|glyph| glyph == '#'), 633);
+        assert_eq!(
+            schematic.sum_adjacent(|glyph| glyph == '*'),
+            467 + 35 + 617 + 755 + 598
+        );
+        assert_eq!(schematic.sum_adjacent(|glyph| glyph == '%'), 0);
+
+        let arity = |glyph, arity| {
+            schematic
+                .symbols_with_arity(glyph, arity)
+                .map(|(symbol, _)| (symbol.row, symbol.column, symbol.glyph))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(arity(Some('*'), 1), [(4, 3, '*')]);
+        assert_eq!(
+            arity(None, 1),
+            [(3, 6, '#'), (4, 3, '*'), (5, 5, '+'), (8, 3, '$')]
+        );
+        assert_eq!(arity(None, 2), [(1, 3, '*'), (8, 5, '*')]);
+        assert_eq!(arity(None, 3), []);
+
+        let ratios = |glyph, arity| {
+            schematic
+                .gear_ratios(glyph, arity)
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+        };
+        assert_eq!(ratios('*', 1), [617]);
+        assert_eq!(ratios('#', 1), [633]);
+        assert_eq!(ratios('*', 2), [16345, 451490]);
+    }
+
+    #[test]
+    fn three_numbers_around_a_symbol() {
+        let schematic = day2_generator("1.2\n.*.\n..3").unwrap();
+        assert_eq!(schematic.symbols_with_arity(None, 3).count(), 1);
+        assert_eq!(schematic.sum_ratios('*', 3).unwrap(), 6);
+        assert_eq!(schematic.sum_ratios('*', 2).unwrap(), 0);
+    }
+
+    #[test]
+    fn ratios_do_not_overflow() {
+        assert_eq!(solve("99999*99999"), (199998, 9999800001));
+        assert_eq!(
+            solve("4294967295*4294967295"),
+            (2 * u32::MAX as u64, u32::MAX as u64 * u32::MAX as u64)
+        );
+
+        let schematic =
+            day2_generator("4294967295.4294967295\n..........*..........\n.........4294967295..")
+                .unwrap();
+        assert!(matches!(
+            schematic.sum_ratios('*', 3),
+            Err(SchematicError::RatioTooLarge {
+                line: 2,
+                column: 11
+            })
+        ));
+    }
+
+    #[test]
+    fn plain_rendering() {
+        let schematic = day2_generator(EXAMPLE).unwrap();
+        assert_eq!(
+            schematic.render(Render::Plain),
+            "[467]..114..
+...<*>......
+..[35]..[633].
+......#...
+[617]*......
+.....+.58.
+..[592].....
+......[755].
+...$.<*>....
+.[664].[598]..
+
+gear at line 2, column 4: 467 * 35 = 16345
+gear at line 9, column 6: 755 * 598 = 451490"
+        );
+    }
+
+    #[test]
+    fn numbers_on_every_border() {
+        assert_eq!(solve("1.2\n.*.\n3.4"), (10, 0));
+        assert_eq!(solve("..12\n.*.."), (12, 0));
+        assert_eq!(solve("12..\n..*.\n..34"), (46, 408));
+    }
+
+    #[test]
+    fn numbers_do_not_join_across_rows() {
+        assert_eq!(solve("..1\n2*."), (3, 2));
+        assert_eq!(solve("..1\n2.."), (0, 0));
+    }
+
+    #[test]
+    fn adjacent_numbers_share_a_symbol() {
+        assert_eq!(solve("12*34"), (46, 408));
+        assert_eq!(solve("12.34\n..#.."), (46, 0));
+    }
+
+    #[test]
+    fn single_row_and_single_column() {
+        assert_eq!(solve("5*5"), (10, 25));
+        assert_eq!(solve("1\n*\n2"), (3, 2));
+        assert_eq!(solve("7"), (0, 0));
+    }
+
+    #[test]
+    fn ragged_rows_are_padded() {
+        assert_eq!(solve("1..\n*\n..2"), (1, 0));
+        assert_eq!(solve("..\n.*.5\n......3"), (0, 0));
+        assert_eq!(solve("1\n.*\n..2"), (3, 2));
+    }
+
+    #[test]
+    fn crlf_rows() {
+        assert_eq!(solve("467..\r\n...*.\r\n..35."), (502, 16345));
+    }
+
+    #[test]
+    fn oversized_numbers_are_rejected() {
+        assert!(matches!(
+            day2_generator("..\n.12345678901*"),
+            Err(SchematicError::NumberTooLarge { line: 2, column: 2 })
+        ));
+        assert_eq!(solve("4294967295*"), (u32::MAX as u64, 0));
+    }
 }
@@ -1,5 +1,6 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 
+use itertools::Itertools;
 use nom::{
     bytes::complete::tag,
     character::complete::{self, line_ending, space1},
@@ -7,20 +8,170 @@ use nom::{
     sequence::{delimited, separated_pair, tuple},
     IResult,
 };
-use std::collections::HashSet;
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    io::{self, BufRead},
+};
 
 #[derive(Debug, Clone)]
-struct Game {
-    winning_nums: HashSet<u32>,
-    actual_nums: HashSet<u32>,
-    copies: usize,
+struct Card {
+    id: u32,
+    winning_nums: Vec<u32>,
+    actual_nums: Vec<u32>,
+}
+
+/// What to do when a number shows up more than once on the same side of a card.
+#[derive(Debug, Clone, Copy)]
+enum Duplicates {
+    Reject,
+    /// Repeats are kept and each copy of a number pairs with at most one copy on
+    /// the other side: `5 5 | 5` is one match, `5 5 | 5 5` is two.
+    Count,
+    /// Repeats are dropped: `5 5 | 5` is one match.
+    Dedupe,
+}
+
+#[derive(Debug)]
+enum CardError {
+    Duplicate {
+        id: u32,
+        line: usize,
+        first_line: usize,
+    },
+    Gap {
+        after: u32,
+        next: u32,
+    },
+    RepeatedNumber {
+        id: u32,
+        line: usize,
+        number: u32,
+        side: &'static str,
+    },
+}
+
+impl fmt::Display for CardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CardError::Duplicate {
+                id,
+                line,
+                first_line,
+            } => write!(
+                f,
+                "line {line}: duplicate Card {id} (first seen on line {first_line})"
+            ),
+            CardError::Gap { after, next } => {
+                write!(f, "missing cards between Card {after} and Card {next}")
+            }
+            CardError::RepeatedNumber {
+                id,
+                line,
+                number,
+                side,
+            } => write!(
+                f,
+                "line {line}: Card {id} lists {number} more than once among its {side} numbers"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CardError {}
+
+/// How many points a card is worth for a given number of matches.
+/// A card without any matches is always worth nothing.
+enum ScoringRule {
+    Doubling,
+    Linear,
+    Fibonacci,
+    Custom(Box<dyn Fn(usize) -> u32>),
 }
 
-type Line = Game;
-type Input = Vec<Line>;
+impl ScoringRule {
+    fn points(&self, matches: usize) -> u32 {
+        if matches == 0 {
+            return 0;
+        }
+
+        match self {
+            ScoringRule::Doubling => 1_u32.checked_shl(matches as u32 - 1).unwrap_or(u32::MAX),
+            ScoringRule::Linear => matches as u32,
+            ScoringRule::Fibonacci => {
+                (1..matches)
+                    .fold((1_u32, 1_u32), |(a, b), _| (b, a.saturating_add(b)))
+                    .0
+            }
+            ScoringRule::Custom(rule) => rule(matches),
+        }
+    }
+}
+
+impl Card {
+    /// Applies `policy` to numbers repeated on either side of the card,
+    /// `line` being where the card was read from.
+    fn check_duplicates(mut self, policy: Duplicates, line: usize) -> Result<Self, CardError> {
+        match policy {
+            Duplicates::Reject => {
+                for (side, nums) in [
+                    ("winning", &self.winning_nums),
+                    ("owned", &self.actual_nums),
+                ] {
+                    let repeated = (1..nums.len()).find(|&i| nums[..i].contains(&nums[i]));
+                    if let Some(number) = repeated.map(|i| nums[i]) {
+                        return Err(CardError::RepeatedNumber {
+                            id: self.id,
+                            line,
+                            number,
+                            side,
+                        });
+                    }
+                }
+            }
+            Duplicates::Count => {}
+            Duplicates::Dedupe => {
+                self.winning_nums = self.winning_nums.into_iter().unique().collect();
+                self.actual_nums = self.actual_nums.into_iter().unique().collect();
+            }
+        }
+
+        Ok(self)
+    }
+
+    /// Size of the multiset intersection of the two sides.
+    fn matches(&self) -> usize {
+        let mut winning = self.winning_nums.clone();
+        winning.sort_unstable();
+        let mut owned = self.actual_nums.clone();
+        owned.sort_unstable();
+
+        let (mut w, mut o, mut matches) = (0, 0, 0);
+        while w < winning.len() && o < owned.len() {
+            match winning[w].cmp(&owned[o]) {
+                Ordering::Less => w += 1,
+                Ordering::Greater => o += 1,
+                Ordering::Equal => {
+                    matches += 1;
+                    w += 1;
+                    o += 1;
+                }
+            }
+        }
+        matches
+    }
+
+    fn score(&self, rule: &ScoringRule) -> u32 {
+        rule.points(self.matches())
+    }
+}
+
+type Line = Card;
+type Input = BTreeMap<u32, Line>;
 
 fn parse_line(input: &str) -> IResult<&str, Line> {
-    let (input, _id) = delimited(
+    let (input, id) = delimited(
         tuple((tag("Card"), space1)),
         complete::u32,
         tuple((tag(":"), space1)),
@@ -33,22 +184,56 @@ fn parse_line(input: &str) -> IResult<&str, Line> {
 
     Ok((
         input,
-        Game {
-            winning_nums: wins.into_iter().collect(),
-            actual_nums: actual.into_iter().collect(),
-            copies: 1,
+        Card {
+            id,
+            winning_nums: wins,
+            actual_nums: actual,
         },
     ))
 }
 
-fn parse_input(input: &str) -> IResult<&str, Input> {
-    let (input, list) = separated_list1(line_ending, parse_line)(input)?;
+fn parse_input(input: &str) -> IResult<&str, Vec<Line>> {
+    separated_list1(line_ending, parse_line)(input)
+}
 
-    Ok((input, list.into_iter().collect()))
+/// Keys cards by their own id, whatever order they were listed in.
+/// Unless `allow_gaps` is set, the ids must also form an unbroken run.
+fn build_table(
+    cards: Vec<Line>,
+    allow_gaps: bool,
+    duplicates: Duplicates,
+) -> Result<Input, CardError> {
+    let mut table = Input::new();
+    let mut lines = BTreeMap::new();
+
+    for (index, card) in cards.into_iter().enumerate() {
+        let line = index + 1;
+        if let Some(&first_line) = lines.get(&card.id) {
+            return Err(CardError::Duplicate {
+                id: card.id,
+                line,
+                first_line,
+            });
+        }
+        lines.insert(card.id, line);
+        table.insert(card.id, card.check_duplicates(duplicates, line)?);
+    }
+
+    if !allow_gaps {
+        if let Some((&after, &next)) = table
+            .keys()
+            .zip(table.keys().skip(1))
+            .find(|(&id, &next)| next != id + 1)
+        {
+            return Err(CardError::Gap { after, next });
+        }
+    }
+
+    Ok(table)
 }
 
 #[aoc_generator(day4)]
-fn day4_generator(input: &str) -> Input {
+fn day4_generator(input: &str) -> Result<Input, CardError> {
     let _input = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
 Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
 Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
@@ -58,31 +243,536 @@ Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11";
 
     let (input, output) = parse_input(input).expect("Could not parse input");
     assert!(input.is_empty());
-    output
+    build_table(output, false, Duplicates::Reject)
+}
+
+#[aoc_generator(day4, part1, Count)]
+fn day4_count_generator(input: &str) -> Result<Input, CardError> {
+    let (input, output) = parse_input(input).expect("Could not parse input");
+    assert!(input.is_empty());
+    build_table(output, false, Duplicates::Count)
+}
+
+#[aoc_generator(day4, part1, Dedupe)]
+fn day4_dedupe_generator(input: &str) -> Result<Input, CardError> {
+    let (input, output) = parse_input(input).expect("Could not parse input");
+    assert!(input.is_empty());
+    build_table(output, false, Duplicates::Dedupe)
+}
+
+/// Cards may skip ids; copies meant for a missing id are lost.
+#[aoc_generator(day4, part1, Sparse)]
+fn day4_sparse_generator(input: &str) -> Result<Input, CardError> {
+    let (input, output) = parse_input(input).expect("Could not parse input");
+    assert!(input.is_empty());
+    build_table(output, true, Duplicates::Reject)
+}
+
+#[aoc_generator(day4, part2, Sparse)]
+fn day4_sparse_part2_generator(input: &str) -> Result<Input, CardError> {
+    day4_sparse_generator(input)
+}
+
+/// Saturates at `u32::MAX`, like the points of a single card.
+fn total_score(input: &Input, rule: &ScoringRule) -> u32 {
+    input
+        .values()
+        .fold(0_u32, |total, card| total.saturating_add(card.score(rule)))
 }
 
 #[aoc(day4, part1)]
 fn solve_part1(input: &Input) -> u32 {
-    input
-        .iter()
-        .map(|card| card.actual_nums.intersection(&card.winning_nums).count())
-        .map(|n| 2_u32.pow(n as u32 - 1))
-        .sum()
+    total_score(input, &ScoringRule::Doubling)
+}
+
+#[aoc(day4, part1, Linear)]
+fn solve_part1_linear(input: &Input) -> u32 {
+    total_score(input, &ScoringRule::Linear)
+}
+
+#[aoc(day4, part1, Fibonacci)]
+fn solve_part1_fibonacci(input: &Input) -> u32 {
+    total_score(input, &ScoringRule::Fibonacci)
+}
+
+#[aoc(day4, part1, Triangular)]
+fn solve_part1_triangular(input: &Input) -> u32 {
+    total_score(
+        input,
+        &ScoringRule::Custom(Box::new(|matches| (matches * (matches + 1) / 2) as u32)),
+    )
+}
+
+#[aoc(day4, part1, Count)]
+fn solve_part1_count(input: &Input) -> u32 {
+    solve_part1(input)
+}
+
+#[aoc(day4, part1, Dedupe)]
+fn solve_part1_dedupe(input: &Input) -> u32 {
+    solve_part1(input)
+}
+
+/// Runs the copy cascade, calling `on_copy(from, to, count)` for every batch of copies
+/// a card hands out, and returns how many copies of each card there are at the end.
+fn cascade(input: &Input, mut on_copy: impl FnMut(u32, u32, usize)) -> BTreeMap<u32, usize> {
+    let mut copies = input.keys().map(|&id| (id, 1)).collect::<BTreeMap<_, _>>();
+
+    for (&id, card) in input {
+        let won = copies[&id];
+        // winnings that point past the end of the table (or into a gap) are dropped
+        for next in (id..=u32::MAX).skip(1).take(card.matches()) {
+            if let Some(count) = copies.get_mut(&next) {
+                *count += won;
+                on_copy(id, next, won);
+            }
+        }
+    }
+
+    copies
+}
+
+#[derive(Debug, Default)]
+struct Provenance {
+    copies: usize,
+    sources: BTreeMap<u32, usize>,
+}
+
+/// For every card, how many copies it ended with and which earlier cards handed them out.
+/// The one original of each card isn't attributed to any source.
+fn provenance(input: &Input) -> BTreeMap<u32, Provenance> {
+    let mut provenance = input
+        .keys()
+        .map(|&id| (id, Provenance::default()))
+        .collect::<BTreeMap<_, _>>();
+
+    let copies = cascade(input, |from, to, count| {
+        *provenance
+            .get_mut(&to)
+            .expect("copied card exists")
+            .sources
+            .entry(from)
+            .or_default() += count;
+    });
+
+    for (id, count) in copies {
+        provenance.get_mut(&id).expect("card exists").copies = count;
+    }
+
+    provenance
+}
+
+fn provenance_table(provenance: &BTreeMap<u32, Provenance>) -> String {
+    let mut out = format!("{:>6} {:>10}  from\n", "card", "copies");
+    for (id, card) in provenance {
+        let sources = card
+            .sources
+            .iter()
+            .map(|(from, count)| format!("{from}x{count}"))
+            .join(", ");
+        out += format!("{id:>6} {:>10}  {sources}", card.copies).trim_end();
+        out.push('\n');
+    }
+    out
+}
+
+fn provenance_dot(provenance: &BTreeMap<u32, Provenance>) -> String {
+    let mut out = String::from("digraph scratchcards {\n");
+    for (id, card) in provenance {
+        out += &format!("    {id} [label=\"Card {id}\\n{} copies\"];\n", card.copies);
+        for (from, count) in &card.sources {
+            out += &format!("    {from} -> {id} [label=\"{count}\"];\n");
+        }
+    }
+    out += "}";
+    out
 }
 
 #[aoc(day4, part2)]
 fn solve_part2(input: &Input) -> usize {
-    let mut input = input.clone();
+    cascade(input, |_, _, _| {}).values().sum::<usize>()
+}
+
+/// Part 2 over cards read one line at a time, without building the table. Only the copies
+/// still owed to upcoming cards are kept, in a ring buffer no wider than the most matches
+/// any card has had so far. Cards have to arrive in id order with no gaps.
+fn stream_total(mut reader: impl BufRead, duplicates: Duplicates) -> io::Result<usize> {
+    let mut pending = VecDeque::new();
+    let mut previous: Option<u32> = None;
+    let mut total = 0;
+    let mut line = String::new();
+    let mut line_number = 0;
 
-    for i in 0..input.len() {
-        let next_few = input[i]
-            .actual_nums
-            .intersection(&input[i].winning_nums)
-            .count();
-        for j in i + 1..(i + 1 + next_few) {
-            input[j].copies += input[i].copies;
+    while reader.read_line(&mut line)? > 0 {
+        line_number += 1;
+        let text = line.trim_end();
+        if !text.is_empty() {
+            let (_, card) = parse_line(text)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+            let card = card
+                .check_duplicates(duplicates, line_number)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            if let Some(previous) =
+                previous.filter(|&previous| previous.checked_add(1) != Some(card.id))
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Card {} streamed after Card {previous}", card.id),
+                ));
+            }
+            previous = Some(card.id);
+
+            let copies = 1 + pending.pop_front().unwrap_or(0);
+            total += copies;
+
+            let matches = card.matches();
+            if pending.len() < matches {
+                pending.resize(matches, 0);
+            }
+            pending.iter_mut().take(matches).for_each(|p| *p += copies);
         }
+        line.clear();
     }
 
-    input.iter().map(|i| i.copies).sum::<usize>()
+    Ok(total)
+}
+
+#[aoc(day4, part1, Sparse)]
+fn solve_part1_sparse(input: &Input) -> u32 {
+    solve_part1(input)
+}
+
+#[aoc(day4, part2, Sparse)]
+fn solve_part2_sparse(input: &Input) -> usize {
+    solve_part2(input)
+}
+
+#[aoc(day4, part2, Provenance)]
+fn solve_part2_provenance(input: &Input) -> usize {
+    let provenance = provenance(input);
+    println!("{}", provenance_table(&provenance));
+    provenance.values().map(|card| card.copies).sum()
+}
+
+#[aoc(day4, part2, Dot)]
+fn solve_part2_dot(input: &Input) -> usize {
+    let provenance = provenance(input);
+    println!("{}", provenance_dot(&provenance));
+    provenance.values().map(|card| card.copies).sum()
+}
+
+/// cargo-aoc only hands over the input as one string, so this copy holds the whole
+/// input anyway; `stream_total` itself keeps nothing but the ring buffer.
+#[aoc_generator(day4, part2, Streaming)]
+fn day4_streaming_generator(input: &str) -> String {
+    input.to_string()
+}
+
+#[aoc(day4, part2, Streaming)]
+fn solve_part2_streaming(input: &str) -> io::Result<usize> {
+    stream_total(input.as_bytes(), Duplicates::Reject)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(winning: &[u32], owned: &[u32]) -> Card {
+        Card {
+            id: 1,
+            winning_nums: winning.to_vec(),
+            actual_nums: owned.to_vec(),
+        }
+    }
+
+    #[test]
+    fn no_matches_score_nothing() {
+        let card = card(&[1, 2, 3], &[4, 5, 6]);
+        assert_eq!(card.matches(), 0);
+        for rule in [
+            ScoringRule::Doubling,
+            ScoringRule::Linear,
+            ScoringRule::Fibonacci,
+            ScoringRule::Custom(Box::new(|_| 100)),
+        ] {
+            assert_eq!(card.score(&rule), 0);
+        }
+    }
+
+    #[test]
+    fn doubling() {
+        let rule = ScoringRule::Doubling;
+        assert_eq!(rule.points(1), 1);
+        assert_eq!(rule.points(4), 8);
+        assert_eq!(rule.points(32), 1 << 31);
+        assert_eq!(rule.points(33), u32::MAX);
+        assert_eq!(rule.points(1000), u32::MAX);
+    }
+
+    #[test]
+    fn linear() {
+        let rule = ScoringRule::Linear;
+        assert_eq!(rule.points(1), 1);
+        assert_eq!(rule.points(4), 4);
+        assert_eq!(rule.points(1000), 1000);
+    }
+
+    #[test]
+    fn fibonacci() {
+        let rule = ScoringRule::Fibonacci;
+        let points = (1..=8).map(|matches| rule.points(matches)).collect_vec();
+        assert_eq!(points, [1, 1, 2, 3, 5, 8, 13, 21]);
+        assert_eq!(rule.points(1000), u32::MAX);
+    }
+
+    #[test]
+    fn custom() {
+        let rule = ScoringRule::Custom(Box::new(|matches| matches as u32 * 10));
+        assert_eq!(rule.points(1), 10);
+        assert_eq!(rule.points(1000), 10000);
+    }
+
+    #[test]
+    fn sparse_tables_follow_ids() {
+        let input = "Card 5: 1 2 | 1 2
+Card 1: 1 2 | 1 2
+Card 7: 1 | 1
+Card 4: 1 | 2";
+        assert!(matches!(
+            day4_generator(input),
+            Err(CardError::Gap { after: 1, next: 4 })
+        ));
+
+        let table = day4_sparse_generator(input).expect("valid cards");
+        assert_eq!(table.keys().copied().collect_vec(), [1, 4, 5, 7]);
+        // card 1 copies 2 (missing) and 3 (missing); card 5 copies 6 (missing) and 7
+        let copies = cascade(&table, |_, _, _| {});
+        assert_eq!(copies.values().copied().collect_vec(), [1, 1, 1, 2]);
+        assert_eq!(solve_part2(&table), 5);
+    }
+
+    #[test]
+    fn winnings_past_the_last_id_are_dropped() {
+        let input = format!("Card {}: 1 2 | 1 2", u32::MAX);
+        let table = day4_sparse_generator(&input).expect("valid cards");
+        assert_eq!(solve_part2(&table), 1);
+        assert_eq!(
+            solve_part2(&day4_generator("Card 1: 1 2 | 1 2").unwrap()),
+            1
+        );
+    }
+
+    #[test]
+    fn streaming_requires_ids_in_order() {
+        let total = |input: &str| stream_total(input.as_bytes(), Duplicates::Reject);
+        assert_eq!(
+            total("Card 1: 1 2 | 1 2\nCard 2: 3 | 3\nCard 3: 4 | 5").unwrap(),
+            7
+        );
+        assert!(total("Card 1: 1 | 1\nCard 3: 1 | 1").is_err());
+        assert!(total(&format!("Card {}: 1 | 1\nCard 0: 1 | 1", u32::MAX)).is_err());
+    }
+
+    fn repeats(duplicates: Duplicates, line: &str) -> Result<usize, CardError> {
+        let (_, card) = parse_line(line).expect("valid card");
+        Ok(card.check_duplicates(duplicates, 1)?.matches())
+    }
+
+    #[test]
+    fn repeated_numbers_are_rejected() {
+        assert!(matches!(
+            repeats(Duplicates::Reject, "Card 3: 5 5 | 5"),
+            Err(CardError::RepeatedNumber {
+                id: 3,
+                line: 1,
+                number: 5,
+                side: "winning"
+            })
+        ));
+        assert!(matches!(
+            repeats(Duplicates::Reject, "Card 3: 5 | 7 5 7"),
+            Err(CardError::RepeatedNumber {
+                number: 7,
+                side: "owned",
+                ..
+            })
+        ));
+        assert_eq!(repeats(Duplicates::Reject, "Card 3: 5 6 | 5").unwrap(), 1);
+    }
+
+    #[test]
+    fn repeated_numbers_are_counted() {
+        assert_eq!(repeats(Duplicates::Count, "Card 1: 5 5 | 5").unwrap(), 1);
+        assert_eq!(repeats(Duplicates::Count, "Card 1: 5 | 5 5").unwrap(), 1);
+        assert_eq!(repeats(Duplicates::Count, "Card 1: 5 5 | 5 5").unwrap(), 2);
+        assert_eq!(
+            repeats(Duplicates::Count, "Card 1: 5 5 6 | 5 5 5 6").unwrap(),
+            3
+        );
+    }
+
+    #[test]
+    fn repeated_numbers_are_deduped() {
+        assert_eq!(repeats(Duplicates::Dedupe, "Card 1: 5 5 | 5").unwrap(), 1);
+        assert_eq!(repeats(Duplicates::Dedupe, "Card 1: 5 5 | 5 5").unwrap(), 1);
+        assert_eq!(
+            repeats(Duplicates::Dedupe, "Card 1: 5 5 6 | 5 5 5 6").unwrap(),
+            2
+        );
+    }
+
+    #[test]
+    fn saturated_scores_add_up_safely() {
+        let input = (1..=3)
+            .map(|id| format!("Card {id}: {} | {}", (1..=40).join(" "), (1..=40).join(" ")))
+            .join("\n");
+        let table = day4_generator(&input).unwrap();
+        assert_eq!(solve_part1(&table), u32::MAX);
+    }
+
+    #[test]
+    fn duplicate_ids_are_rejected() {
+        let input = "Card 1: 1 | 2\nCard 2: 1 | 2\nCard 1: 3 | 4";
+        assert!(matches!(
+            day4_generator(input),
+            Err(CardError::Duplicate {
+                id: 1,
+                line: 3,
+                first_line: 1
+            })
+        ));
+        assert!(matches!(
+            day4_sparse_generator(input),
+            Err(CardError::Duplicate { id: 1, .. })
+        ));
+    }
+
+    const EXAMPLE: &str = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
+Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
+Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
+Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
+Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11";
+
+    #[test]
+    fn provenance_of_the_example() {
+        let provenance = provenance(&day4_generator(EXAMPLE).unwrap());
+        let card = &provenance[&5];
+        assert_eq!(card.copies, 14);
+        assert_eq!(
+            card.sources.iter().map(|(&id, &n)| (id, n)).collect_vec(),
+            [(1, 1), (3, 4), (4, 8)]
+        );
+
+        assert_eq!(
+            provenance_table(&provenance),
+            "  card     copies  from
+     1          1
+     2          2  1x1
+     3          4  1x1, 2x2
+     4          8  1x1, 2x2, 3x4
+     5         14  1x1, 3x4, 4x8
+     6          1
+"
+        );
+
+        assert_eq!(
+            provenance_dot(&provenance),
+            r#"digraph scratchcards {
+    1 [label="Card 1\n1 copies"];
+    2 [label="Card 2\n2 copies"];
+    1 -> 2 [label="1"];
+    3 [label="Card 3\n4 copies"];
+    1 -> 3 [label="1"];
+    2 -> 3 [label="2"];
+    4 [label="Card 4\n8 copies"];
+    1 -> 4 [label="1"];
+    2 -> 4 [label="2"];
+    3 -> 4 [label="4"];
+    5 [label="Card 5\n14 copies"];
+    1 -> 5 [label="1"];
+    3 -> 5 [label="4"];
+    4 -> 5 [label="8"];
+    6 [label="Card 6\n1 copies"];
+}"#
+        );
+    }
+
+    /// Reads generated cards one line at a time, as if from a very large file.
+    struct Generated<I> {
+        lines: I,
+        current: Vec<u8>,
+        position: usize,
+    }
+
+    impl<I: Iterator<Item = String>> io::Read for Generated<I> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let available = io::BufRead::fill_buf(self)?;
+            let n = available.len().min(buf.len());
+            buf[..n].copy_from_slice(&available[..n]);
+            io::BufRead::consume(self, n);
+            Ok(n)
+        }
+    }
+
+    impl<I: Iterator<Item = String>> BufRead for Generated<I> {
+        fn fill_buf(&mut self) -> io::Result<&[u8]> {
+            if self.position == self.current.len() {
+                self.current = self.lines.next().map_or(vec![], String::into_bytes);
+                self.position = 0;
+            }
+            Ok(&self.current[self.position..])
+        }
+
+        fn consume(&mut self, amount: usize) {
+            self.position += amount;
+        }
+    }
+
+    /// Mostly losing cards, with a few winners sprinkled in so copies stay bounded.
+    fn generated_card(id: u32) -> Card {
+        let matches = match id {
+            _ if id.is_multiple_of(1000) => 5,
+            _ if id.is_multiple_of(10) => 3,
+            _ if id.is_multiple_of(3) => 1,
+            _ => 0,
+        };
+        Card {
+            id,
+            winning_nums: (1..=5).collect(),
+            actual_nums: (1..=5)
+                .map(|n| if n <= matches { n } else { n + 50 })
+                .collect(),
+        }
+    }
+
+    fn card_line(card: Card) -> String {
+        format!(
+            "Card {}: {} | {}\n",
+            card.id,
+            card.winning_nums.iter().join(" "),
+            card.actual_nums.iter().join(" ")
+        )
+    }
+
+    #[test]
+    fn streams_a_million_cards() {
+        const CARDS: u32 = 1_000_000;
+        let reader = Generated {
+            lines: (1..=CARDS).map(generated_card).map(card_line),
+            current: vec![],
+            position: 0,
+        };
+        let streamed = stream_total(reader, Duplicates::Reject).expect("cards in order");
+
+        let table = (1..=CARDS).map(|id| (id, generated_card(id))).collect();
+        assert_eq!(streamed, solve_part2(&table));
+    }
+
+    #[test]
+    fn example_scores() {
+        let table = day4_generator(EXAMPLE).expect("valid cards");
+        assert_eq!(solve_part1(&table), 13);
+        assert_eq!(solve_part2(&table), 30);
+    }
 }
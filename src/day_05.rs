@@ -3,30 +3,47 @@ use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
 use nom::{
     bytes::complete::tag,
-    character::complete::{self, alpha1, line_ending, space1},
+    character::complete::{alpha1, digit1, line_ending, space1},
+    combinator::map_res,
     multi::separated_list1,
     sequence::{delimited, separated_pair, terminated, tuple},
-    IResult,
+    IResult, Offset,
+};
+use num::{PrimInt, Unsigned};
+
+use crate::interval_set::IntervalSet;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    ops::RangeInclusive,
+    str::FromStr,
 };
-use std::collections::HashMap;
 
-#[derive(Debug)]
-struct Range {
-    source_start: u32,
-    destination_start: u32,
-    size: u32,
+/// The last value of the `size` long run starting at `start`, unless the run is
+/// empty or would go past `T::MAX`.
+fn checked_last<T: PrimInt + Unsigned>(start: T, size: T) -> Option<T> {
+    size.checked_sub(&T::one())
+        .and_then(|offset| start.checked_add(&offset))
 }
 
-impl Range {
-    fn source_end(&self) -> u32 {
-        self.source_start + self.size - 1
-    }
+fn last<T: PrimInt + Unsigned>(start: T, size: T) -> T {
+    checked_last(start, size).expect("range to fit in its integer type")
+}
 
-    fn destination_end(&self) -> u32 {
-        self.destination_start + self.size - 1
+#[derive(Debug)]
+struct Range<T> {
+    source_start: T,
+    destination_start: T,
+    size: T,
+    line: usize,
+}
+
+impl<T: PrimInt + Unsigned> Range<T> {
+    fn source_end(&self) -> T {
+        last(self.source_start, self.size)
     }
 
-    fn map_within(&self, from: u32) -> u32 {
+    fn map_within(&self, from: T) -> T {
         if from < self.source_start || from > self.source_end() {
             return from;
         }
@@ -35,60 +52,519 @@ impl Range {
     }
 }
 
+/// Maps `start..=end` onto `destination..` one to one.
+#[derive(Debug, Clone, Copy)]
+struct Piece<T> {
+    start: T,
+    end: T,
+    destination: T,
+}
+
+impl<T: PrimInt + Unsigned> Piece<T> {
+    fn apply(&self, from: T) -> T {
+        self.destination + (from - self.start)
+    }
+
+    fn image(&self) -> RangeInclusive<T> {
+        self.destination..=self.apply(self.end)
+    }
+}
+
+/// A map over every value of `T`, as a sorted run of pieces each shifting its
+/// values by a constant. Values no range mentions map to themselves.
+#[derive(Debug, Clone)]
+struct PiecewiseMap<T> {
+    pieces: Vec<Piece<T>>,
+}
+
+impl<T: PrimInt + Unsigned> PiecewiseMap<T> {
+    fn identity() -> Self {
+        PiecewiseMap {
+            pieces: vec![Piece {
+                start: T::zero(),
+                end: T::max_value(),
+                destination: T::zero(),
+            }],
+        }
+    }
+
+    /// One almanac stage. Where ranges overlap, the one listed first wins, just
+    /// like looking a value up with `find`.
+    fn from_ranges(ranges: &[Range<T>]) -> Self {
+        let mut covered = IntervalSet::default();
+        let mut pieces = vec![];
+
+        for range in ranges {
+            let source = range.source_start..=range.source_end();
+            if covered.contains_range(source.clone()) {
+                continue;
+            }
+
+            let fresh = IntervalSet::from(source).difference(&covered);
+            pieces.extend(fresh.iter().map(|r| Piece {
+                start: *r.start(),
+                end: *r.end(),
+                destination: range.map_within(*r.start()),
+            }));
+            covered = covered.union(&fresh);
+        }
+
+        pieces.extend(
+            covered
+                .complement(T::zero()..=T::max_value())
+                .iter()
+                .map(|r| Piece {
+                    start: *r.start(),
+                    end: *r.end(),
+                    destination: *r.start(),
+                }),
+        );
+        pieces.sort_unstable_by_key(|p| p.start);
+
+        PiecewiseMap { pieces }
+    }
+
+    fn overlapping(&self, range: RangeInclusive<T>) -> impl Iterator<Item = &Piece<T>> {
+        let first = self.pieces.partition_point(|p| p.start <= *range.start()) - 1;
+        self.pieces[first..]
+            .iter()
+            .take_while(move |p| p.start <= *range.end())
+    }
+
+    fn get(&self, value: T) -> T {
+        self.overlapping(value..=value)
+            .next()
+            .expect("pieces to cover every value")
+            .apply(value)
+    }
+
+    fn map_set(&self, set: &IntervalSet<T>) -> IntervalSet<T> {
+        self.pieces
+            .iter()
+            .flat_map(|p| {
+                IntervalSet::from(p.start..=p.end)
+                    .intersection(set)
+                    .iter()
+                    .map(|r| p.apply(*r.start())..=p.apply(*r.end()))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Every value that maps into `set`, including values that reach it by
+    /// being left alone in the gaps between ranges.
+    fn preimage(&self, set: &IntervalSet<T>) -> IntervalSet<T> {
+        self.pieces
+            .iter()
+            .flat_map(|p| {
+                IntervalSet::from(p.image())
+                    .intersection(set)
+                    .iter()
+                    .map(|r| {
+                        (p.start + (*r.start() - p.destination))
+                            ..=(p.start + (*r.end() - p.destination))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// The single map that applies `self` and then `other`.
+    fn compose(&self, other: &Self) -> Self {
+        let mut pieces: Vec<Piece<T>> = vec![];
+        for piece in &self.pieces {
+            let image = piece.image();
+            let (lo, hi) = image.clone().into_inner();
+            for next in other.overlapping(image) {
+                let from = next.start.max(lo);
+                let to = next.end.min(hi);
+                let composed = Piece {
+                    start: piece.start + (from - lo),
+                    end: piece.start + (to - lo),
+                    destination: next.apply(from),
+                };
+                match pieces.last_mut() {
+                    // same shift as the piece before, so extend it instead
+                    Some(previous)
+                        if previous.apply(previous.end).checked_add(&T::one())
+                            == Some(composed.destination) =>
+                    {
+                        previous.end = composed.end
+                    }
+                    _ => pieces.push(composed),
+                }
+            }
+        }
+        PiecewiseMap { pieces }
+    }
+}
+
+impl<T: PrimInt + Unsigned + fmt::Display> fmt::Display for PiecewiseMap<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:>20} {:>20} {:>20}", "start", "end", "destination")?;
+        for piece in &self.pieces {
+            writeln!(
+                f,
+                "{:>20} {:>20} {:>20}",
+                piece.start, piece.end, piece.destination
+            )?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
-struct SeedMap {
-    seeds: Vec<u32>,
-    maps: HashMap<String, (String, Vec<Range>)>,
-}
-
-type Input = SeedMap;
-
-fn parse_range(input: &str) -> IResult<&str, Range> {
-    let (input, (destination_start, source_start, size)) = tuple((
-        terminated(complete::u32, space1),
-        terminated(complete::u32, space1),
-        complete::u32,
-    ))(input)?;
-
-    Ok((
-        input,
-        Range {
-            destination_start,
-            source_start,
-            size,
-        },
-    ))
-}
-
-fn parse_map(input: &str) -> IResult<&str, (String, (String, Vec<Range>))> {
-    let (input, (from, to)) = terminated(
-        separated_pair(alpha1, tag("-to-"), alpha1),
-        tuple((tag(" map:"), line_ending)),
-    )(input)?;
-    let (input, range) = separated_list1(line_ending, parse_range)(input)?;
+struct Stage<T> {
+    source: String,
+    destination: String,
+    ranges: Vec<Range<T>>,
+}
+
+#[derive(Debug)]
+enum AlmanacError {
+    DuplicateSource(String),
+    Cycle(String),
+    Unreachable(String),
+    NoPath { from: String, to: String },
+    Invalid(Vec<Issue>),
+}
+
+#[derive(Debug)]
+enum Issue {
+    OddSeeds,
+    /// `pair` counts the seeds' start/length pairs from 1.
+    EmptySeeds {
+        pair: usize,
+    },
+    SeedsTooLarge {
+        pair: usize,
+    },
+    ZeroSized {
+        stage: String,
+        line: usize,
+    },
+    TooLarge {
+        stage: String,
+        line: usize,
+    },
+    OverlappingSources {
+        stage: String,
+        line: usize,
+        other: usize,
+    },
+    CollidingDestinations {
+        stage: String,
+        line: usize,
+        other: usize,
+    },
+}
+
+impl Issue {
+    fn line(&self) -> usize {
+        match self {
+            Issue::OddSeeds | Issue::EmptySeeds { .. } | Issue::SeedsTooLarge { .. } => 1,
+            Issue::ZeroSized { line, .. }
+            | Issue::TooLarge { line, .. }
+            | Issue::OverlappingSources { line, .. }
+            | Issue::CollidingDestinations { line, .. } => *line,
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::OddSeeds => write!(
+                f,
+                "line 1 (seeds): seeds can't be read as start/length pairs"
+            ),
+            Issue::EmptySeeds { pair } => {
+                write!(f, "line 1 (seeds): seed range {pair} is empty")
+            }
+            Issue::SeedsTooLarge { pair } => write!(
+                f,
+                "line 1 (seeds): seed range {pair} runs past the largest value"
+            ),
+            Issue::ZeroSized { stage, line } => write!(f, "line {line} ({stage}): range is empty"),
+            Issue::TooLarge { stage, line } => {
+                write!(
+                    f,
+                    "line {line} ({stage}): range runs past the largest value"
+                )
+            }
+            Issue::OverlappingSources { stage, line, other } => {
+                write!(f, "line {line} ({stage}): source overlaps line {other}")
+            }
+            Issue::CollidingDestinations { stage, line, other } => {
+                write!(
+                    f,
+                    "line {line} ({stage}): destination overlaps line {other}"
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Display for AlmanacError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlmanacError::DuplicateSource(category) => {
+                write!(f, "more than one map starts from {category}")
+            }
+            AlmanacError::Cycle(category) => write!(f, "maps loop back around to {category}"),
+            AlmanacError::Unreachable(category) => {
+                write!(f, "{category} can't be reached from seed")
+            }
+            AlmanacError::NoPath { from, to } => write!(f, "no maps lead from {from} to {to}"),
+            AlmanacError::Invalid(issues) => write!(f, "{}", issues.iter().join("\n")),
+        }
+    }
+}
+
+impl std::error::Error for AlmanacError {}
+
+/// The seeds plus a graph of categories, where every stage is an edge from its
+/// source category to its destination. Each category has at most one way out,
+/// so the path between two categories (if any) is found by just following it.
+#[derive(Debug)]
+struct Almanac<T> {
+    seeds: Vec<T>,
+    stages: Vec<Stage<T>>,
+    by_source: HashMap<String, usize>,
+}
+
+impl<T: PrimInt + Unsigned> Almanac<T> {
+    /// Requires that no category has two maps out of it, that following the maps
+    /// never loops, that every category can be reached from `seed`, and that the
+    /// maps from `seed` end up at `location`.
+    fn new(seeds: Vec<T>, stages: Vec<Stage<T>>) -> Result<Self, AlmanacError> {
+        let mut by_source = HashMap::new();
+        for (index, stage) in stages.iter().enumerate() {
+            if by_source.insert(stage.source.clone(), index).is_some() {
+                return Err(AlmanacError::DuplicateSource(stage.source.clone()));
+            }
+        }
+
+        let almanac = Almanac {
+            seeds,
+            stages,
+            by_source,
+        };
+
+        for stage in &almanac.stages {
+            let mut seen = HashSet::new();
+            let mut category = stage.source.as_str();
+            while let Some(next) = almanac.next(category) {
+                if !seen.insert(category) {
+                    return Err(AlmanacError::Cycle(category.to_string()));
+                }
+                category = &next.destination;
+            }
+        }
+
+        let mut reachable = HashSet::from(["seed"]);
+        let mut category = "seed";
+        while let Some(next) = almanac.next(category) {
+            category = &next.destination;
+            reachable.insert(category);
+        }
+        if category != "location" {
+            return Err(AlmanacError::NoPath {
+                from: "seed".to_string(),
+                to: "location".to_string(),
+            });
+        }
+        if let Some(stage) = almanac
+            .stages
+            .iter()
+            .find(|s| !reachable.contains(s.source.as_str()))
+        {
+            return Err(AlmanacError::Unreachable(stage.source.clone()));
+        }
+
+        Ok(almanac)
+    }
+
+    /// Everything that would make a lookup ambiguous or panic, ordered by the
+    /// line it was found on. With `seed_ranges`, the seeds must come in pairs
+    /// that each describe a non-empty range.
+    fn validate(&self, seed_ranges: bool) -> Vec<Issue> {
+        let mut issues = vec![];
+        if seed_ranges && !self.seeds.len().is_multiple_of(2) {
+            issues.push(Issue::OddSeeds);
+        }
+        if seed_ranges {
+            for (index, pair) in self.seeds.chunks_exact(2).enumerate() {
+                match checked_last(pair[0], pair[1]) {
+                    Some(_) => {}
+                    None if pair[1].is_zero() => issues.push(Issue::EmptySeeds { pair: index + 1 }),
+                    None => issues.push(Issue::SeedsTooLarge { pair: index + 1 }),
+                }
+            }
+        }
+
+        for stage in &self.stages {
+            let name = format!("{}-to-{}", stage.source, stage.destination);
+            let mut sources = vec![];
+            let mut destinations = vec![];
+
+            for range in &stage.ranges {
+                match (
+                    checked_last(range.source_start, range.size),
+                    checked_last(range.destination_start, range.size),
+                ) {
+                    _ if range.size.is_zero() => issues.push(Issue::ZeroSized {
+                        stage: name.clone(),
+                        line: range.line,
+                    }),
+                    (Some(source_end), Some(destination_end)) => {
+                        sources.push((range.source_start, source_end, range.line));
+                        destinations.push((range.destination_start, destination_end, range.line));
+                    }
+                    _ => issues.push(Issue::TooLarge {
+                        stage: name.clone(),
+                        line: range.line,
+                    }),
+                }
+            }
+
+            issues.extend(
+                overlaps(sources).map(|(line, other)| Issue::OverlappingSources {
+                    stage: name.clone(),
+                    line,
+                    other,
+                }),
+            );
+            issues.extend(overlaps(destinations).map(|(line, other)| {
+                Issue::CollidingDestinations {
+                    stage: name.clone(),
+                    line,
+                    other,
+                }
+            }));
+        }
+
+        issues.sort_by_key(Issue::line);
+        issues
+    }
+
+    fn next(&self, category: &str) -> Option<&Stage<T>> {
+        self.by_source
+            .get(category)
+            .map(|&index| &self.stages[index])
+    }
+
+    /// Every stage on the way from `from` to `to`, composed into one map.
+    fn between(&self, from: &str, to: &str) -> Result<PiecewiseMap<T>, AlmanacError> {
+        let mut chain = PiecewiseMap::identity();
+        let mut category = from;
+
+        while category != to {
+            let stage = self.next(category).ok_or_else(|| AlmanacError::NoPath {
+                from: from.to_string(),
+                to: to.to_string(),
+            })?;
+            chain = chain.compose(&PiecewiseMap::from_ranges(&stage.ranges));
+            category = &stage.destination;
+        }
+
+        Ok(chain)
+    }
+
+    fn chain(&self) -> PiecewiseMap<T> {
+        self.between("seed", "location")
+            .expect("validated almanac to lead from seed to location")
+    }
+}
+
+/// Pairs up the lines of any `(start, end, line)` spans that overlap, each as
+/// (later line, earlier line).
+fn overlaps<T: Ord + Copy>(mut spans: Vec<(T, T, usize)>) -> impl Iterator<Item = (usize, usize)> {
+    spans.sort_unstable();
+    let mut found = vec![];
+    for (i, &(start, _, line)) in spans.iter().enumerate() {
+        found.extend(
+            spans[..i]
+                .iter()
+                .filter(|&&(_, end, _)| end >= start)
+                .map(|&(_, _, other)| (line.max(other), line.min(other))),
+        );
+    }
+    found.sort_unstable();
+    found.into_iter()
+}
+
+type Input = Almanac<u64>;
+
+fn number<T: FromStr>(input: &str) -> IResult<&str, T> {
+    map_res(digit1, str::parse)(input)
+}
+
+/// The (1-based) line of `original` that `rest` starts on.
+fn line_of(original: &str, rest: &str) -> usize {
+    original[..original.offset(rest)].matches('\n').count() + 1
+}
+
+fn parse_range<'a, T: FromStr>(
+    original: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Range<T>> {
+    move |input| {
+        let line = line_of(original, input);
+        let (input, (destination_start, source_start, size)) = tuple((
+            terminated(number, space1),
+            terminated(number, space1),
+            number,
+        ))(input)?;
+
+        Ok((
+            input,
+            Range {
+                destination_start,
+                source_start,
+                size,
+                line,
+            },
+        ))
+    }
+}
 
-    Ok((input, (from.to_string(), (to.to_string(), range))))
+fn parse_map<'a, T: FromStr>(
+    original: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Stage<T>> {
+    move |input| {
+        let (input, (from, to)) = terminated(
+            separated_pair(alpha1, tag("-to-"), alpha1),
+            tuple((tag(" map:"), line_ending)),
+        )(input)?;
+        let (input, ranges) = separated_list1(line_ending, parse_range(original))(input)?;
+
+        Ok((
+            input,
+            Stage {
+                source: from.to_string(),
+                destination: to.to_string(),
+                ranges,
+            },
+        ))
+    }
 }
 
-fn parse_input(input: &str) -> IResult<&str, Input> {
+fn parse_input<T: FromStr>(input: &str) -> IResult<&str, (Vec<T>, Vec<Stage<T>>)> {
+    let original = input;
     let (input, seeds) = delimited(
         tag("seeds: "),
-        separated_list1(tag(" "), complete::u32),
+        separated_list1(tag(" "), number),
         tuple((line_ending, line_ending)),
     )(input)?;
-    let (input, maps) = separated_list1(tuple((line_ending, line_ending)), parse_map)(input)?;
+    let (input, stages) =
+        separated_list1(tuple((line_ending, line_ending)), parse_map(original))(input)?;
 
-    Ok((
-        input,
-        SeedMap {
-            seeds,
-            maps: maps.into_iter().collect::<HashMap<String, _>>(),
-        },
-    ))
+    Ok((input, (seeds, stages)))
 }
 
-#[aoc_generator(day5)]
-fn day5_generator(input: &str) -> Input {
+/// Parses and validates the almanac, which for `seed_ranges` must also list its
+/// seeds in pairs.
+fn generate(input: &str, seed_ranges: bool) -> Result<Input, AlmanacError> {
     let _input = "seeds: 79 14 55 13
 
 seed-to-soil map:
@@ -123,144 +599,366 @@ humidity-to-location map:
 60 56 37
 56 93 4";
 
-    let (input, output) = parse_input(input).expect("Could not parse input");
+    let (input, (seeds, stages)) = parse_input(input).expect("Could not parse input");
     assert!(input.is_empty());
-    output
+
+    let almanac = Almanac::new(seeds, stages)?;
+    let issues = almanac.validate(seed_ranges);
+    if !issues.is_empty() {
+        return Err(AlmanacError::Invalid(issues));
+    }
+    Ok(almanac)
+}
+
+#[aoc_generator(day5, part1)]
+fn day5_generator(input: &str) -> Result<Input, AlmanacError> {
+    generate(input, false)
+}
+
+#[aoc_generator(day5, part2)]
+fn day5_part2_generator(input: &str) -> Result<Input, AlmanacError> {
+    generate(input, true)
 }
 
 #[aoc(day5, part1)]
-fn solve_part1(input: &Input) -> u32 {
-    let mut state = "seed".to_string();
-    let mut vals = input.seeds.clone();
-
-    while let Some(next) = input.maps.get(&state) {
-        state = next.0.clone();
-        vals.iter_mut().for_each(|v| {
-            if let Some(map_to) = next
-                .1
+fn solve_part1(input: &Input) -> u64 {
+    let chain = input.chain();
+
+    input
+        .seeds
+        .iter()
+        .map(|&seed| chain.get(seed))
+        .min()
+        .expect("Some value to exist")
+}
+
+fn seed_ranges(input: &Input) -> IntervalSet<u64> {
+    input
+        .seeds
+        .chunks(2)
+        .map(|s| s[0]..=last(s[0], s[1]))
+        .collect()
+}
+
+#[aoc(day5, part2)]
+fn solve_part2(input: &Input) -> u64 {
+    input
+        .chain()
+        .map_set(&seed_ranges(input))
+        .min()
+        .expect("min to exist")
+}
+
+/// Location ranges the chain can produce, lowest first.
+fn locations(chain: &PiecewiseMap<u64>) -> Vec<RangeInclusive<u64>> {
+    let mut locations = chain.pieces.iter().map(Piece::image).collect::<Vec<_>>();
+    locations.sort_unstable_by_key(|r| *r.start());
+    locations
+}
+
+#[aoc(day5, part1, Inverse)]
+fn solve_part1_inverse(input: &Input) -> u64 {
+    let chain = input.chain();
+
+    locations(&chain)
+        .into_iter()
+        .find_map(|location| {
+            let seeds = chain.preimage(&IntervalSet::from(location));
+            input
+                .seeds
                 .iter()
-                .find(|n| *v >= n.source_start && *v <= n.source_end())
-            {
-                *v = map_to.map_within(*v);
-            }
-        });
-    }
-
-    *vals.iter().min().expect("Some value to exist")
-}
-
-trait Diff {
-    fn diff(self, subtract: &Self) -> Vec<Self>
-    where
-        Self: Sized;
-}
-
-impl<T> Diff for std::ops::RangeInclusive<T>
-where
-    T: Sized
-        + std::cmp::PartialOrd
-        + std::ops::Add<u32, Output = T>
-        + std::ops::Sub<u32, Output = T>
-        + std::fmt::Debug
-        + Copy,
-{
-    fn diff(self, subtract: &Self) -> Vec<Self> {
-        if subtract.end() < self.start() || subtract.start() > self.end() {
-            // non-conflicting
-            vec![self]
-        } else if subtract.start() <= self.start() {
-            if subtract.end() >= self.end() {
-                vec![]
-            } else {
-                // starts before, and ends before
-                vec![(*subtract.end() + 1)..=*self.end()]
+                .filter(|&&seed| seeds.contains(seed))
+                .map(|&seed| chain.get(seed))
+                .min()
+        })
+        .expect("Some value to exist")
+}
+
+#[aoc(day5, part2, Inverse)]
+fn solve_part2_inverse(input: &Input) -> u64 {
+    let chain = input.chain();
+    let seeds = seed_ranges(input);
+
+    locations(&chain)
+        .into_iter()
+        .find_map(|location| {
+            let found = chain
+                .preimage(&IntervalSet::from(location))
+                .intersection(&seeds);
+            chain.map_set(&found).min()
+        })
+        .expect("min to exist")
+}
+
+#[aoc(day5, part2, Table)]
+fn solve_part2_table(input: &Input) -> u64 {
+    println!("{}", input.chain());
+    solve_part2(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "seeds: 79 14 55 13
+
+seed-to-soil map:
+50 98 2
+52 50 48
+
+soil-to-fertilizer map:
+0 15 37
+37 52 2
+39 0 15
+
+fertilizer-to-water map:
+49 53 8
+0 11 42
+42 0 7
+57 7 4
+
+water-to-light map:
+88 18 7
+18 25 70
+
+light-to-temperature map:
+45 77 23
+81 45 19
+68 64 13
+
+temperature-to-humidity map:
+0 69 1
+1 0 69
+
+humidity-to-location map:
+60 56 37
+56 93 4";
+
+    fn solve(input: &str) -> (u64, u64) {
+        (
+            solve_part1(&day5_generator(input).expect("valid almanac")),
+            solve_part2(&day5_part2_generator(input).expect("valid almanac")),
+        )
+    }
+
+    #[test]
+    fn last_value_is_checked() {
+        assert_eq!(checked_last(u64::MAX, 1), Some(u64::MAX));
+        assert_eq!(checked_last(u64::MAX - 1, 2), Some(u64::MAX));
+        assert_eq!(checked_last(u64::MAX - 1, 3), None);
+        assert_eq!(checked_last(5_u64, 0), None);
+
+        let range = Range {
+            source_start: u32::MAX - 1,
+            destination_start: 0_u32,
+            size: 2,
+            line: 1,
+        };
+        assert_eq!(range.source_end(), u32::MAX);
+        assert_eq!(range.map_within(u32::MAX), 1);
+        assert_eq!(range.map_within(u32::MAX - 2), u32::MAX - 2);
+    }
+
+    #[test]
+    fn maps_must_lead_to_location() {
+        let input = "seeds: 1 2
+
+seed-to-soil map:
+5 0 10";
+        assert!(matches!(
+            day5_generator(input),
+            Err(AlmanacError::NoPath { from, to }) if from == "seed" && to == "location"
+        ));
+    }
+
+    #[test]
+    fn seed_ranges_are_validated() {
+        let input = |seeds: &str| format!("seeds: {seeds}\n\nseed-to-location map:\n0 0 1");
+        let issues = |seeds: &str| match day5_part2_generator(&input(seeds)) {
+            Err(AlmanacError::Invalid(issues)) => {
+                issues.iter().map(|i| i.to_string()).collect_vec()
             }
-        } else if subtract.end() >= self.end() {
-            vec![*self.start()..=(*subtract.start())]
-        } else {
-            vec![
-                *self.start()..=(*subtract.start()),
-                (*subtract.end() + 1)..=*self.end(),
+            Err(e) => panic!("unexpected error {e}"),
+            Ok(_) => vec![],
+        };
+
+        assert_eq!(issues("5 0"), ["line 1 (seeds): seed range 1 is empty"]);
+        assert_eq!(
+            issues("1 2 18446744073709551615 2"),
+            ["line 1 (seeds): seed range 2 runs past the largest value"]
+        );
+        assert_eq!(
+            issues("1 2 3"),
+            ["line 1 (seeds): seeds can't be read as start/length pairs"]
+        );
+        assert!(issues("18446744073709551615 1").is_empty());
+        // part 1 reads the same seeds one at a time
+        assert!(day5_generator(&input("5 0")).is_ok());
+    }
+
+    #[test]
+    fn stage_ranges_are_validated() {
+        let input = "seeds: 1 2
+
+seed-to-location map:
+0 10 0
+5 20 10
+6 25 10
+0 18446744073709551615 2";
+        let Err(AlmanacError::Invalid(issues)) = day5_generator(input) else {
+            panic!("expected the almanac to be rejected");
+        };
+        assert_eq!(
+            issues.iter().map(|i| i.to_string()).collect_vec(),
+            [
+                "line 4 (seed-to-location): range is empty",
+                "line 6 (seed-to-location): source overlaps line 5",
+                "line 6 (seed-to-location): destination overlaps line 5",
+                "line 7 (seed-to-location): range runs past the largest value",
             ]
-        }
+        );
     }
-}
 
-#[aoc(day5, part2)]
-fn solve_part2(input: &Input) -> u32 {
-    let mut state = "seed".to_string();
-    let mut vals = input
-        .seeds
-        .chunks(2)
-        .map(|s| (s[0]..=(s[0] + s[1] - 1)))
-        .collect_vec();
+    #[test]
+    fn ranges_ending_at_u64_max() {
+        let input = "seeds: 18446744073709551615 1
 
-    while let Some(map_to) = input.maps.get(&state) {
-        state = map_to.0.clone();
+seed-to-location map:
+0 18446744073709551614 2";
+        assert_eq!(solve(input), (1, 1));
+    }
 
-        vals = vals
-            .iter()
-            .flat_map(|seed_range| {
-                let lookup_ranges = map_to
-                    .1
+    #[test]
+    fn ranges_past_u32_max() {
+        let input = "seeds: 4294967295 10
+
+seed-to-location map:
+5000000000 4294967290 20";
+        assert_eq!(solve(input), (10, 5000000005));
+    }
+
+    #[test]
+    fn example() {
+        assert_eq!(solve(EXAMPLE), (35, 46));
+    }
+
+    #[test]
+    fn composed_chain_matches_each_stage_in_turn() {
+        let almanac = day5_generator(EXAMPLE).expect("valid almanac");
+        let chain = almanac.chain();
+
+        for seed in 0..=200 {
+            let mut value = seed;
+            let mut category = "seed";
+            while let Some(stage) = almanac.next(category) {
+                value = stage
+                    .ranges
                     .iter()
-                    .filter(|lookup_range| {
-                        seed_range.end() >= &lookup_range.source_start
-                            && seed_range.start() <= &lookup_range.source_end()
-                    })
-                    .map(|lookup_range| {
-                        if lookup_range.source_start <= *seed_range.start() {
-                            if lookup_range.source_end() >= *seed_range.end() {
-                                // fully encompassed in this range
-                                (
-                                    *seed_range.start(),
-                                    *seed_range.end(),
-                                    lookup_range.map_within(*seed_range.start())
-                                        ..=lookup_range.map_within(*seed_range.end()),
-                                )
-                            } else {
-                                (
-                                    *seed_range.start(),
-                                    lookup_range.source_end(),
-                                    lookup_range.map_within(*seed_range.start())
-                                        ..=lookup_range.destination_end(),
-                                )
-                            }
-                        } else if lookup_range.source_end() <= *seed_range.end() {
-                            // conflict is contained within the range
-                            (
-                                lookup_range.source_start,
-                                lookup_range.source_end(),
-                                lookup_range.destination_start..=lookup_range.destination_end(),
-                            )
-                        } else {
-                            // conflict ends after source
-                            (
-                                lookup_range.source_start,
-                                *seed_range.end(),
-                                lookup_range.destination_start
-                                    ..=lookup_range.map_within(*seed_range.end()),
-                            )
-                        }
-                    })
-                    .collect_vec();
-
-                let mut new_range = vec![seed_range.clone()];
-                for t in lookup_ranges.iter() {
-                    new_range = new_range
-                        .iter()
-                        .flat_map(|v| v.clone().diff(&(t.0..=t.1)))
-                        .collect_vec();
-                }
+                    .find(|r| (r.source_start..=r.source_end()).contains(&value))
+                    .map_or(value, |r| r.map_within(value));
+                category = &stage.destination;
+            }
+            assert_eq!(chain.get(seed), value, "seed {seed}");
+        }
+    }
 
-                for t in lookup_ranges {
-                    new_range.push(t.2);
-                }
+    #[test]
+    fn breakpoint_table() {
+        let almanac = day5_generator(EXAMPLE).expect("valid almanac");
+        assert_eq!(
+            almanac
+                .between("seed", "soil")
+                .expect("seed-to-soil map")
+                .to_string(),
+            "               start                  end          destination
+                   0                   49                    0
+                  50                   97                   52
+                  98                   99                   50
+                 100 18446744073709551615                  100
+"
+        );
+    }
 
-                new_range
-            })
-            .collect_vec();
+    #[test]
+    fn preimage_matches_a_forward_scan() {
+        let chain = day5_generator(EXAMPLE).expect("valid almanac").chain();
+        let targets: IntervalSet<u64> = [35..=60, 150..=150].into_iter().collect();
+        let sources = chain.preimage(&targets);
+
+        // nothing maps 150 anywhere, so it only gets there through the gaps
+        assert_eq!(chain.get(150), 150);
+        assert!(sources.contains(150));
+        for seed in 0..=200 {
+            assert_eq!(
+                sources.contains(seed),
+                targets.contains(chain.get(seed)),
+                "seed {seed}"
+            );
+        }
+    }
+
+    #[test]
+    fn inverse_solutions() {
+        assert_eq!(
+            solve_part1_inverse(&day5_generator(EXAMPLE).expect("valid almanac")),
+            35
+        );
+        assert_eq!(
+            solve_part2_inverse(&day5_part2_generator(EXAMPLE).expect("valid almanac")),
+            46
+        );
     }
 
-    vals.iter().map(|r| *r.start()).min().expect("min to exist")
+    #[test]
+    fn maps_between_any_two_categories() {
+        let almanac = day5_generator(EXAMPLE).expect("valid almanac");
+        let soil_to_humidity = almanac.between("soil", "humidity").expect("a path");
+        assert_eq!(soil_to_humidity.get(81), 78);
+        assert!(matches!(
+            almanac.between("humidity", "soil"),
+            Err(AlmanacError::NoPath { .. })
+        ));
+    }
+
+    #[test]
+    fn malformed_category_graphs() {
+        let duplicate = "seeds: 1 2
+
+seed-to-soil map:
+0 0 1
+
+seed-to-location map:
+0 0 1";
+        assert!(matches!(
+            day5_generator(duplicate),
+            Err(AlmanacError::DuplicateSource(category)) if category == "seed"
+        ));
+
+        let cycle = "seeds: 1 2
+
+seed-to-soil map:
+0 0 1
+
+soil-to-location map:
+0 0 1
+
+location-to-soil map:
+0 0 1";
+        assert!(matches!(
+            day5_generator(cycle),
+            Err(AlmanacError::Cycle(category)) if category == "soil"
+        ));
+
+        let unreachable = "seeds: 1 2
+
+seed-to-location map:
+0 0 1
+
+water-to-light map:
+0 0 1";
+        assert!(matches!(
+            day5_generator(unreachable),
+            Err(AlmanacError::Unreachable(category)) if category == "water"
+        ));
+    }
 }
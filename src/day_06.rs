@@ -8,6 +8,10 @@ use nom::{
     sequence::{preceded, separated_pair, tuple},
     IResult,
 };
+use num::{integer::Roots, BigUint, CheckedMul, Integer};
+use std::{fmt, ops::RangeInclusive, str::FromStr};
+
+use crate::interval_set::IntervalSet;
 
 #[derive(Debug)]
 struct Race<T> {
@@ -15,23 +19,279 @@ struct Race<T> {
     record_distance: T,
 }
 
-fn parse_input(input: &str) -> IResult<&str, Vec<Race<u16>>> {
+impl<T: Integer + Roots + Clone + CheckedMul> Race<T> {
+    /// `hold * (time - hold) > record`, compared through a division so that the
+    /// product is never formed and can't overflow.
+    fn wins(&self, hold: &T) -> bool {
+        !hold.is_zero()
+            && *hold <= self.time
+            && self.time.clone() - hold.clone() > self.record_distance.clone() / hold.clone()
+    }
+
+    /// Finds the shortest hold time that beats the record without trying each one.
+    /// Winners are the integers strictly between the roots of `h * (time - h) = record`,
+    /// so an integer square root of the discriminant lands within a step of the first.
+    /// If the discriminant doesn't fit in `T`, the rising half is binary searched instead.
+    fn first_win(&self) -> Option<T> {
+        let two = T::one() + T::one();
+        let best = self.time.clone() / two.clone();
+        if !self.wins(&best) {
+            return None;
+        }
+
+        let square = self.time.checked_mul(&self.time);
+        let needed = (two.clone() * two.clone()).checked_mul(&self.record_distance);
+        let mut first = match square.zip(needed) {
+            Some((square, needed)) => (self.time.clone() - (square - needed).sqrt()) / two,
+            None => {
+                let (mut low, mut high) = (T::zero(), best);
+                while low < high {
+                    let middle = low.clone() + (high.clone() - low.clone()) / two.clone();
+                    if self.wins(&middle) {
+                        high = middle;
+                    } else {
+                        low = middle + T::one();
+                    }
+                }
+                low
+            }
+        };
+
+        while !self.wins(&first) {
+            first = first + T::one();
+        }
+        while first > T::zero() && self.wins(&(first.clone() - T::one())) {
+            first = first - T::one();
+        }
+
+        Some(first)
+    }
+
+    /// Winners are symmetric around `time / 2`, so they run up to `time - first`.
+    fn ways_to_win(&self) -> T {
+        match self.first_win() {
+            Some(first) => self.time.clone() - first.clone() - first + T::one(),
+            None => T::zero(),
+        }
+    }
+}
+
+/// How holding the button turns into distance. Holding for less than `min_hold`
+/// does nothing; otherwise every millisecond held adds `charge_rate` to the speed,
+/// up to `max_speed`. While moving, the boat loses `decay` speed every millisecond.
+#[derive(Debug, Clone, Copy)]
+struct BoatModel {
+    charge_rate: u64,
+    max_speed: Option<u64>,
+    decay: u64,
+    min_hold: u64,
+}
+
+impl Default for BoatModel {
+    fn default() -> Self {
+        BoatModel {
+            charge_rate: 1,
+            max_speed: None,
+            decay: 0,
+            min_hold: 0,
+        }
+    }
+}
+
+impl BoatModel {
+    fn speed(&self, hold: u64) -> u64 {
+        if hold < self.min_hold {
+            return 0;
+        }
+        let speed = self.charge_rate.saturating_mul(hold);
+        self.max_speed.map_or(speed, |max| speed.min(max))
+    }
+
+    fn distance(&self, hold: u64, time: u64) -> u64 {
+        let speed = self.speed(hold);
+        let moving = time.saturating_sub(hold);
+        if self.decay == 0 {
+            return speed.saturating_mul(moving);
+        }
+
+        // speed drops to nothing after `speed / decay` (rounded up) milliseconds
+        let steps = moving.min(speed.div_ceil(self.decay));
+        // `decay * (steps - 1)` never exceeds `speed`, but the totals can pass `u64::MAX`
+        let travelled = u128::from(steps) * u128::from(speed);
+        let lost = u128::from(self.decay * steps.saturating_sub(1)) * u128::from(steps) / 2;
+        u64::try_from(travelled - lost).unwrap_or(u64::MAX)
+    }
+
+    /// Every hold time that beats the record, as runs of consecutive times.
+    fn winning_holds(&self, race: &Race<u64>) -> IntervalSet<u64> {
+        if self.charge_rate == 0 || race.time == 0 {
+            return IntervalSet::default();
+        }
+        if self.decay > 0 {
+            return self.scan(race);
+        }
+
+        // while still charging, `rate * h * (time - h) > record` is the plain race
+        // against a record of `record / rate`, so the closed form still applies
+        let charging_until = self.charging_until(race.time);
+        let scaled = Race {
+            time: race.time,
+            record_distance: race.record_distance / self.charge_rate,
+        };
+        let charging = scaled
+            .first_win()
+            .map(|first| IntervalSet::from(first..=(race.time - first)))
+            .unwrap_or_default()
+            .intersection(&IntervalSet::from(self.min_hold..=charging_until));
+
+        // past that the speed is stuck at the cap, so holding longer only loses time
+        let capped = match self.max_speed {
+            Some(max) if max > 0 && charging_until < race.time => {
+                let last = race.time.checked_sub(race.record_distance / max + 1);
+                last.map(|last| (charging_until + 1).max(self.min_hold)..=last)
+                    .map(IntervalSet::from)
+                    .unwrap_or_default()
+            }
+            _ => IntervalSet::default(),
+        };
+
+        charging.union(&capped)
+    }
+
+    /// The longest hold (within `time`) that still adds speed.
+    fn charging_until(&self, time: u64) -> u64 {
+        self.max_speed
+            .map_or(time, |max| (max / self.charge_rate.max(1)).min(time))
+    }
+
+    /// The hold time that goes furthest (the shortest, on a tie), and how far that is.
+    fn best_hold(&self, time: u64) -> (u64, u64) {
+        let candidates = if self.decay > 0 {
+            (0..=time).collect_vec()
+        } else {
+            // charging, distance peaks around the middle; once capped it only falls
+            let charging_until = self.charging_until(time);
+            let clamp = |hold: u64| hold.clamp(self.min_hold.min(charging_until), charging_until);
+            vec![
+                0,
+                self.min_hold,
+                clamp(time / 2),
+                clamp(time.div_ceil(2)),
+                charging_until + 1,
+            ]
+        };
+
+        candidates
+            .into_iter()
+            .filter(|&hold| hold <= time)
+            .map(|hold| (hold, self.distance(hold, time)))
+            .min_by_key(|&(hold, distance)| (std::cmp::Reverse(distance), hold))
+            .unwrap_or((0, 0))
+    }
+
+    fn report(&self, race: &Race<u64>) -> RaceReport {
+        let holds = self.winning_holds(race);
+        let (best_hold, best_distance) = self.best_hold(race.time);
+
+        RaceReport {
+            time: race.time,
+            record: race.record_distance,
+            first: holds.min(),
+            last: holds.max(),
+            count: holds
+                .count()
+                .expect("fewer winning holds than the race time"),
+            best_hold,
+            best_distance,
+        }
+    }
+
+    fn scan(&self, race: &Race<u64>) -> IntervalSet<u64> {
+        (0..=race.time)
+            .filter(|&hold| self.distance(hold, race.time) > race.record_distance)
+            .fold(vec![], |mut runs: Vec<RangeInclusive<u64>>, hold| {
+                match runs.last_mut() {
+                    Some(run) if *run.end() + 1 == hold => *run = *run.start()..=hold,
+                    _ => runs.push(hold..=hold),
+                }
+                runs
+            })
+            .into_iter()
+            .collect()
+    }
+}
+
+/// Everything worth knowing about one race under a model.
+#[derive(Debug)]
+struct RaceReport {
+    time: u64,
+    record: u64,
+    first: Option<u64>,
+    last: Option<u64>,
+    count: u64,
+    best_hold: u64,
+    best_distance: u64,
+}
+
+impl RaceReport {
+    const HEADER: &'static str =
+        "      time     record      first       last      count  best hold   distance     margin";
+
+    /// How much further than the record the best hold goes (negative if it can't win).
+    fn margin(&self) -> i128 {
+        self.best_distance as i128 - self.record as i128
+    }
+}
+
+impl fmt::Display for RaceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let or_dash = |v: Option<u64>| v.map_or("-".to_string(), |v| v.to_string());
+        write!(
+            f,
+            "{:>10} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+            self.time,
+            self.record,
+            or_dash(self.first),
+            or_dash(self.last),
+            self.count,
+            self.best_hold,
+            self.best_distance,
+            self.margin()
+        )
+    }
+}
+
+fn print_reports<'a>(reports: impl IntoIterator<Item = &'a RaceReport>) {
+    println!("{}", RaceReport::HEADER);
+    reports.into_iter().for_each(|report| println!("{report}"));
+}
+
+impl Race<u64> {
+    fn ways_to_win_linear(&self) -> u64 {
+        match (1..self.time).find(|hold| self.wins(hold)) {
+            Some(first_time) => self.time - 2 * first_time + 1,
+            None => 0,
+        }
+    }
+}
+
+fn parse_input(input: &str) -> IResult<&str, Vec<Race<u64>>> {
     let (input, (times, distances)) = separated_pair(
         preceded(
             tuple((tag("Time:"), space1)),
-            separated_list1(space1, complete::u16),
+            separated_list1(space1, complete::u64),
         ),
         line_ending,
         preceded(
             tuple((tag("Distance:"), space1)),
-            separated_list1(space1, complete::u16),
+            separated_list1(space1, complete::u64),
         ),
     )(input)?;
 
     let races = times
         .iter()
         .zip(distances.iter())
-        .map(|(&time, &record_distance)| Race::<u16> {
+        .map(|(&time, &record_distance)| Race {
             time,
             record_distance,
         })
@@ -40,7 +300,7 @@ fn parse_input(input: &str) -> IResult<&str, Vec<Race<u16>>> {
     Ok((input, races))
 }
 
-fn parse_input_big_number(input: &str) -> IResult<&str, Race<u64>> {
+fn parse_input_big_number<T: FromStr>(input: &str) -> IResult<&str, Race<T>> {
     let (input, times) = preceded(
         tuple((tag("Time:"), space1)),
         separated_list1(space1, digit1),
@@ -50,12 +310,12 @@ fn parse_input_big_number(input: &str) -> IResult<&str, Race<u64>> {
         separated_list1(space1, digit1),
     )(input)?;
 
-    let time = times.concat().parse::<u64>().expect("large number");
-    let record_distance = distances.join("").parse::<u64>().expect("large number");
+    let time = times.concat().parse::<T>().ok().expect("large number");
+    let record_distance = distances.join("").parse::<T>().ok().expect("large number");
 
     Ok((
         input,
-        Race::<u64> {
+        Race {
             time,
             record_distance,
         },
@@ -63,26 +323,229 @@ fn parse_input_big_number(input: &str) -> IResult<&str, Race<u64>> {
 }
 
 #[aoc(day6, part1)]
-fn solve_part1(input: &str) -> usize {
+fn solve_part1(input: &str) -> u64 {
     let (_, input) = parse_input(input).expect("Could not parse input");
+    let model = BoatModel::default();
 
     input
         .iter()
         .map(|race| {
-            let first_time = (1..race.time)
-                .find(|time| time * (race.time - time) > race.record_distance)
-                .expect("Race not possible");
-            (first_time..=(race.time - first_time)).count()
+            model
+                .winning_holds(race)
+                .count()
+                .expect("fewer winning holds than the race time")
         })
         .product()
 }
 
+#[aoc(day6, part1, Report)]
+fn solve_part1_report(input: &str) -> u64 {
+    let (_, input) = parse_input(input).expect("Could not parse input");
+    let model = BoatModel::default();
+
+    let reports = input.iter().map(|race| model.report(race)).collect_vec();
+    print_reports(&reports);
+    reports.iter().map(|report| report.count).product()
+}
+
+#[aoc(day6, part1, Linear)]
+fn solve_part1_linear(input: &str) -> u64 {
+    let (_, input) = parse_input(input).expect("Could not parse input");
+
+    input.iter().map(Race::ways_to_win_linear).product()
+}
+
 #[aoc(day6, part2)]
-fn solve_part2(input: &str) -> usize {
-    let (_, input) = parse_input_big_number(input).expect("Could not parse input");
+fn solve_part2(input: &str) -> u64 {
+    let (_, input) = parse_input_big_number::<u64>(input).expect("Could not parse input");
+
+    BoatModel::default()
+        .winning_holds(&input)
+        .count()
+        .expect("fewer winning holds than the race time")
+}
+
+#[aoc(day6, part2, Report)]
+fn solve_part2_report(input: &str) -> u64 {
+    let (_, input) = parse_input_big_number::<u64>(input).expect("Could not parse input");
+
+    let report = BoatModel::default().report(&input);
+    print_reports([&report]);
+    report.count
+}
+
+#[aoc(day6, part2, Linear)]
+fn solve_part2_linear(input: &str) -> u64 {
+    let (_, input) = parse_input_big_number::<u64>(input).expect("Could not parse input");
+
+    input.ways_to_win_linear()
+}
+
+#[aoc(day6, part2, U128)]
+fn solve_part2_u128(input: &str) -> u128 {
+    let (_, input) = parse_input_big_number::<u128>(input).expect("Could not parse input");
+
+    input.ways_to_win()
+}
+
+#[aoc(day6, part2, BigUint)]
+fn solve_part2_big(input: &str) -> BigUint {
+    let (_, input) = parse_input_big_number::<BigUint>(input).expect("Could not parse input");
+
+    input.ways_to_win()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closed_form_matches_linear_scan() {
+        for time in 0..=60_u64 {
+            for record in 0..=time * time / 4 + 2 {
+                let race = Race {
+                    time,
+                    record_distance: record,
+                };
+                let expected = race.ways_to_win_linear();
+                assert_eq!(race.ways_to_win(), expected, "{race:?}");
+
+                let wide = Race {
+                    time: time as u128,
+                    record_distance: record as u128,
+                };
+                assert_eq!(wide.ways_to_win(), expected as u128, "{race:?}");
+
+                let big = Race {
+                    time: BigUint::from(time),
+                    record_distance: BigUint::from(record),
+                };
+                assert_eq!(big.ways_to_win(), BigUint::from(expected), "{race:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn example() {
+        let input = "Time:      7  15   30\nDistance:  9  40  200";
+        assert_eq!(solve_part1(input), 288);
+        assert_eq!(solve_part1_linear(input), 288);
+        assert_eq!(solve_part2(input), 71503);
+        assert_eq!(solve_part2_u128(input), 71503);
+        assert_eq!(solve_part2_big(input), BigUint::from(71503_u32));
+    }
+
+    #[test]
+    fn times_too_large_to_square() {
+        let input = "Time: 5000000000\nDistance: 1000";
+        assert_eq!(solve_part2(input), 4999999999);
+        assert_eq!(solve_part2(input) as u128, solve_part2_u128(input));
+
+        let race = Race {
+            time: u64::MAX,
+            record_distance: u64::MAX - 1,
+        };
+        assert_eq!(race.ways_to_win() as u128, {
+            Race {
+                time: u64::MAX as u128,
+                record_distance: (u64::MAX - 1) as u128,
+            }
+            .ways_to_win()
+        });
+    }
+
+    fn models() -> impl Iterator<Item = BoatModel> {
+        itertools::iproduct!(
+            0..=3,
+            [None, Some(0), Some(1), Some(5), Some(12)],
+            [0, 1, 3],
+            0..=4
+        )
+        .map(|(charge_rate, max_speed, decay, min_hold)| BoatModel {
+            charge_rate,
+            max_speed,
+            decay,
+            min_hold,
+        })
+    }
+
+    #[test]
+    fn winning_holds_match_a_scan() {
+        for model in models() {
+            for time in 0..=16 {
+                for record_distance in 0..=50 {
+                    let race = Race {
+                        time,
+                        record_distance,
+                    };
+                    assert_eq!(
+                        model.winning_holds(&race),
+                        model.scan(&race),
+                        "{model:?} {race:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn best_hold_is_the_furthest() {
+        for model in models() {
+            for time in 0..=30 {
+                let furthest = (0..=time)
+                    .map(|hold| (hold, model.distance(hold, time)))
+                    .min_by_key(|&(hold, distance)| (std::cmp::Reverse(distance), hold))
+                    .expect("at least one hold");
+                assert_eq!(model.best_hold(time), furthest, "{model:?} {time}");
+            }
+        }
+    }
+
+    #[test]
+    fn distance_saturates() {
+        let model = BoatModel {
+            decay: 1,
+            ..BoatModel::default()
+        };
+        assert_eq!(model.distance(u64::MAX / 2, u64::MAX), u64::MAX);
+        assert_eq!(model.distance(4, 10), 4 + 3 + 2 + 1);
+    }
+
+    #[test]
+    fn reports() {
+        let model = BoatModel::default();
+        let report = model.report(&Race {
+            time: 7,
+            record_distance: 9,
+        });
+        assert_eq!(
+            (report.first, report.last, report.count),
+            (Some(2), Some(5), 4)
+        );
+        assert_eq!((report.best_hold, report.best_distance), (3, 12));
+        assert_eq!(report.margin(), 3);
+        assert_eq!(
+            report.to_string(),
+            "         7          9          2          5          4          3         12          3"
+        );
+
+        let kerned = model.report(&Race {
+            time: 71530,
+            record_distance: 940200,
+        });
+        assert_eq!(
+            kerned.to_string(),
+            "     71530     940200         14      71516      71503      35765 1279135225 1278195025"
+        );
 
-    let first_time = (1..input.time)
-        .find(|time| time * (input.time - time) > input.record_distance)
-        .expect("Race not possible");
-    (first_time..=(input.time - first_time)).count()
+        let unwinnable = model.report(&Race {
+            time: 3,
+            record_distance: 100,
+        });
+        assert_eq!(unwinnable.margin(), -98);
+        assert_eq!(
+            unwinnable.to_string(),
+            "         3        100          -          -          0          1          2        -98"
+        );
+    }
 }
@@ -1,55 +1,313 @@
 use aoc_runner_derive::{aoc, aoc_generator};
+use std::{collections::BTreeMap, fmt};
 
-use itertools::Itertools;
+use itertools::{Either, Itertools};
 use nom::{
     bytes::complete::tag,
-    character::complete::{self, line_ending, one_of},
-    multi::separated_list1,
+    character::complete::{self, line_ending, satisfy},
+    multi::{many1, separated_list1},
     sequence::preceded,
-    IResult, Parser,
+    IResult,
 };
+use num::integer::binomial;
+use rand::{seq::SliceRandom, Rng};
 
 #[derive(Debug)]
 struct Hand {
-    cards: [u8; 5],
+    cards: Vec<char>,
     bid: u16,
 }
 
-fn card_to_u8(c: char) -> u8 {
-    match c {
-        '2' => 2,
-        '3' => 3,
-        '4' => 4,
-        '5' => 5,
-        '6' => 6,
-        '7' => 7,
-        '8' => 8,
-        '9' => 9,
-        'T' => 10,
-        'J' => 11,
-        'Q' => 12,
-        'K' => 13,
-        'A' => 14,
-        _ => unreachable!(),
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum HandType {
+    HighCard,
+    OnePair,
+    TwoPair,
+    ThreeOfAKind,
+    FullHouse,
+    FourOfAKind,
+    FiveOfAKind,
+}
+
+/// How many of each label a hand holds, largest first, once wild cards have
+/// joined the biggest group. Comparing patterns lexicographically ranks hands
+/// of any size, and for five cards matches the order of `HandType`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Pattern(Vec<usize>);
+
+impl Pattern {
+    /// The puzzle's name for this pattern, for five-card hands only.
+    fn hand_type(&self) -> Option<HandType> {
+        match self.0[..] {
+            [5] => Some(HandType::FiveOfAKind),
+            [4, 1] => Some(HandType::FourOfAKind),
+            [3, 2] => Some(HandType::FullHouse),
+            [3, 1, 1] => Some(HandType::ThreeOfAKind),
+            [2, 2, 1] => Some(HandType::TwoPair),
+            [2, 1, 1, 1] => Some(HandType::OnePair),
+            [1, 1, 1, 1, 1] => Some(HandType::HighCard),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.hand_type() {
+            Some(hand_type) => write!(f, "{hand_type:?}"),
+            None => write!(f, "{}", self.0.iter().join("+")),
+        }
+    }
+}
+
+/// Where wild cards sit when two hands of the same type are compared card by card.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WildRank {
+    /// Below every natural card, as jokers do in part 2.
+    Lowest,
+    /// Wherever the card order puts them.
+    Natural,
+}
+
+/// A deck's card order from weakest to strongest, which of its cards are wild,
+/// and how those wild cards break ties. Wild cards stand in for whatever makes
+/// the hand strongest.
+#[derive(Debug, Clone)]
+struct Rules {
+    order: Vec<char>,
+    wild: Vec<char>,
+    wild_rank: WildRank,
+}
+
+impl Rules {
+    fn new(order: &str) -> Self {
+        Rules {
+            order: order.chars().collect(),
+            wild: vec![],
+            wild_rank: WildRank::Natural,
+        }
+    }
+
+    fn standard() -> Self {
+        Self::new("23456789TJQKA")
+    }
+
+    fn aces_low() -> Self {
+        Self::new("A23456789TJQK")
+    }
+
+    fn jokers() -> Self {
+        Self::standard().with_wild("J", WildRank::Lowest)
+    }
+
+    fn with_wild(self, wild: &str, wild_rank: WildRank) -> Self {
+        Rules {
+            wild: wild.chars().collect(),
+            wild_rank,
+            ..self
+        }
+    }
+
+    /// Makes sure every card in the (one hand per line) input is in the deck.
+    fn check(&self, input: &Input) -> Result<(), HandError> {
+        for (index, hand) in input.iter().enumerate() {
+            if let Some(&card) = hand.cards.iter().find(|card| !self.order.contains(card)) {
+                return Err(HandError::UnknownCard {
+                    card,
+                    line: index + 1,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn is_wild(&self, card: char) -> bool {
+        self.wild.contains(&card)
+    }
+
+    /// Tiebreak strength of `card`. With `WildRank::Lowest` the wild cards keep
+    /// their relative order but all sit beneath the natural ones.
+    fn strength(&self, card: char) -> u8 {
+        let ladder = match self.wild_rank {
+            WildRank::Natural => Either::Left(self.order.iter()),
+            WildRank::Lowest => Either::Right(
+                self.order
+                    .iter()
+                    .filter(|c| self.is_wild(**c))
+                    .chain(self.order.iter().filter(|c| !self.is_wild(**c))),
+            ),
+        };
+
+        ladder
+            .into_iter()
+            .position(|&c| c == card)
+            .unwrap_or_else(|| panic!("{card} is not in the deck")) as u8
+    }
+}
+
+impl Hand {
+    /// The hand's type under `rules`, for five-card hands. Other sizes only have a
+    /// `Pattern`.
+    fn classify(&self, rules: &Rules) -> Option<HandType> {
+        self.pattern(rules).hand_type()
+    }
+
+    fn pattern(&self, rules: &Rules) -> Pattern {
+        let mut counts = self
+            .cards
+            .iter()
+            .filter(|&&card| !rules.is_wild(card))
+            .counts()
+            .into_values()
+            .sorted_unstable()
+            .rev()
+            .collect_vec();
+
+        let wild = self.cards.len() - counts.iter().sum::<usize>();
+        match counts.first_mut() {
+            Some(most) => *most += wild,
+            None => counts.push(wild),
+        }
+
+        Pattern(counts)
+    }
+
+    /// The hand as played: each wild card replaced by the label it stands in for,
+    /// which is the most common natural card (the strongest of those on a tie),
+    /// or the strongest natural card in the deck if every card is wild.
+    fn played(&self, rules: &Rules) -> String {
+        let natural = self
+            .cards
+            .iter()
+            .filter(|&&card| !rules.is_wild(card))
+            .counts()
+            .into_iter()
+            .max_by_key(|&(&card, count)| (count, rules.strength(card)))
+            .map(|(&card, _)| card)
+            .or_else(|| {
+                rules
+                    .order
+                    .iter()
+                    .rev()
+                    .find(|&&card| !rules.is_wild(card))
+                    .copied()
+            });
+
+        self.cards
+            .iter()
+            .map(|&card| match natural {
+                Some(natural) if rules.is_wild(card) => natural,
+                _ => card,
+            })
+            .collect()
+    }
+
+    /// What hands are sorted by under `rules`: `a.rank(rules) < b.rank(rules)`
+    /// exactly when `a` is the weaker hand.
+    fn rank(&self, rules: &Rules) -> RankedHand<'_> {
+        RankedHand {
+            pattern: self.pattern(rules),
+            strengths: self
+                .cards
+                .iter()
+                .map(|&card| rules.strength(card))
+                .collect(),
+            hand: self,
+        }
+    }
+}
+
+/// A hand as scored under some rules, ordered from weakest to strongest:
+/// by pattern first, then card by card.
+#[derive(Debug)]
+struct RankedHand<'a> {
+    pattern: Pattern,
+    strengths: Vec<u8>,
+    hand: &'a Hand,
+}
+
+impl Ord for RankedHand<'_> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (&self.pattern, &self.strengths).cmp(&(&other.pattern, &other.strengths))
+    }
+}
+
+impl PartialOrd for RankedHand<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for RankedHand<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
     }
 }
 
+impl Eq for RankedHand<'_> {}
+
 type Input = Vec<Hand>;
 
-fn parse_hand(input: &str) -> IResult<&str, Hand> {
-    let mut cards = [0, 0, 0, 0, 0];
-
-    let (input, card) = one_of("23456789TJQKA").map(card_to_u8).parse(input)?;
-    cards[0] = card;
-    let (input, card) = one_of("23456789TJQKA").map(card_to_u8).parse(input)?;
-    cards[1] = card;
-    let (input, card) = one_of("23456789TJQKA").map(card_to_u8).parse(input)?;
-    cards[2] = card;
-    let (input, card) = one_of("23456789TJQKA").map(card_to_u8).parse(input)?;
-    cards[3] = card;
-    let (input, card) = one_of("23456789TJQKA").map(card_to_u8).parse(input)?;
-    cards[4] = card;
+#[derive(Debug)]
+pub enum HandError {
+    UnknownCard {
+        card: char,
+        line: usize,
+    },
+    /// Every hand must hold as many cards as the first one, `expected`.
+    MixedSizes {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// A known card, when working out odds, that the deck doesn't hold.
+    NotInDeck {
+        card: char,
+    },
+    TooManyCopies {
+        card: char,
+        copies: usize,
+    },
+    DeckTooSmall {
+        unknown: usize,
+        size: usize,
+    },
+}
 
+impl fmt::Display for HandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HandError::UnknownCard { card, line } => {
+                write!(f, "line {line}: {card} is not in the deck")
+            }
+            HandError::MixedSizes {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {line}: hand has {found} cards, but the first hand has {expected}"
+            ),
+            HandError::NotInDeck { card } => write!(f, "{card} is not in the deck"),
+            HandError::TooManyCopies { card, copies } => {
+                write!(f, "the deck only has {copies} of {card}")
+            }
+            HandError::DeckTooSmall { unknown, size } => {
+                write!(f, "can't draw {unknown} from {size} cards")
+            }
+        }
+    }
+}
+
+impl std::error::Error for HandError {}
+
+/// Any label is accepted here; it is the `Rules` that decide what a deck holds.
+fn label(input: &str) -> IResult<&str, char> {
+    satisfy(|c| c.is_ascii_alphanumeric())(input)
+}
+
+fn parse_hand(input: &str) -> IResult<&str, Hand> {
+    let (input, cards) = many1(label)(input)?;
     let (input, bid) = preceded(tag(" "), complete::u16)(input)?;
 
     Ok((input, Hand { cards, bid }))
@@ -60,7 +318,7 @@ fn parse_input(input: &str) -> IResult<&str, Input> {
 }
 
 #[aoc_generator(day7)]
-fn day7_generator(input: &str) -> Input {
+fn day7_generator(input: &str) -> Result<Input, HandError> {
     let _input = "32T3K 765
 T55J5 684
 KK677 28
@@ -70,95 +328,563 @@ J2T3K 11";
 
     let (input, output) = parse_input(input).expect("Could not parse input");
     assert!(input.is_empty());
-    output
-}
 
-#[aoc(day7, part1)]
-fn solve_part1(input: &Input) -> u32 {
-    struct Helper {
-        counts: Vec<usize>,
-        cards: [u8; 5],
-        bid: u16,
+    let expected = output.first().map_or(0, |hand| hand.cards.len());
+    if let Some((index, hand)) = output
+        .iter()
+        .enumerate()
+        .find(|(_, hand)| hand.cards.len() != expected)
+    {
+        return Err(HandError::MixedSizes {
+            line: index + 1,
+            expected,
+            found: hand.cards.len(),
+        });
     }
+    Ok(output)
+}
 
-    input
+/// Every hand from weakest to strongest, so a hand's rank is its index plus one.
+fn ranking<'a>(input: &'a Input, rules: &Rules) -> Result<Vec<RankedHand<'a>>, HandError> {
+    rules.check(input)?;
+    Ok(input
+        .iter()
+        .map(|hand| hand.rank(rules))
+        .sorted_unstable()
+        .collect())
+}
+
+fn total_winnings(input: &Input, rules: &Rules) -> Result<u32, HandError> {
+    Ok(ranking(input, rules)?
         .iter()
-        .map(|hand| Helper {
-            counts: hand
-                .cards
-                .iter()
-                .counts()
-                .values()
-                .sorted_unstable()
-                .rev()
-                .copied()
-                .collect_vec(),
-            cards: hand.cards,
-            bid: hand.bid,
-        })
-        .sorted_unstable_by(|left, right| match left.counts.cmp(&right.counts) {
-            std::cmp::Ordering::Equal => {
-                // compare elements
-                // Vec ord is element by element
-                left.cards.cmp(&right.cards)
-            }
-            o => o,
-        })
         .enumerate()
-        .map(|(rank, hand)| (rank as u32 + 1) * hand.bid as u32)
-        .sum::<u32>()
+        .map(|(rank, hand)| (rank as u32 + 1) * hand.hand.bid as u32)
+        .sum::<u32>())
 }
 
-#[aoc(day7, part2)]
-fn solve_part2(input: &Input) -> u32 {
-    #[derive(Debug)]
-    struct Helper {
-        counts: Vec<usize>,
-        jokers: u8,
-        cards: [u8; 5],
-        bid: u16,
-    }
+#[derive(Debug, Copy, Clone)]
+enum Table {
+    Plain,
+    Csv,
+}
 
-    input
+/// One row per hand in rank order: the cards as dealt and as played once wild
+/// cards are substituted, its type, bid and winnings.
+fn ranking_table(input: &Input, rules: &Rules, format: Table) -> Result<String, HandError> {
+    let width = input
         .iter()
-        .map(|hand| Helper {
-            counts: hand
-                .cards
-                .into_iter()
-                .filter(|&v| v != 11) // filter jokers from this count
-                .counts()
-                .values()
-                .sorted_unstable()
-                .rev()
-                .copied()
-                .collect_vec(),
-            jokers: hand.cards.iter().filter(|&&card| card == 11).count() as u8,
-            cards: hand.cards.map(|v| if v == 11 { 1 } else { v }),
-            bid: hand.bid,
-        })
-        .map(|mut v| {
-            v.counts
-                .first_mut()
-                .map(|count| *count += v.jokers as usize)
-                .or_else(|| {
-                    v.counts.push(v.jokers as usize);
-                    Some(())
-                });
-            v
+        .map(|hand| hand.cards.len())
+        .max()
+        .unwrap_or_default()
+        .max(6);
+
+    let mut out = match format {
+        Table::Plain => format!(
+            "{:>6}  {:<width$}  {:<width$}  {:<14} {:>6} {:>10}\n",
+            "rank", "hand", "played", "type", "bid", "winnings"
+        ),
+        Table::Csv => String::from("rank,hand,played,type,bid,winnings\n"),
+    };
+
+    for (rank, ranked) in ranking(input, rules)?.iter().enumerate() {
+        let rank = rank as u32 + 1;
+        let hand = ranked.hand.cards.iter().collect::<String>();
+        let played = ranked.hand.played(rules);
+        let pattern = match ranked.hand.classify(rules) {
+            Some(hand_type) => format!("{hand_type:?}"),
+            None => ranked.pattern.to_string(),
+        };
+        let bid = ranked.hand.bid;
+        let winnings = rank * bid as u32;
+
+        out += &match format {
+            Table::Plain => format!(
+                "{rank:>6}  {hand:<width$}  {played:<width$}  {pattern:<14} {bid:>6} {winnings:>10}\n"
+            ),
+            Table::Csv => format!("{rank},{hand},{played},{pattern},{bid},{winnings}\n"),
+        };
+    }
+
+    Ok(out)
+}
+
+/// Probability of each pattern a hand can end up with, and whether it was worked out
+/// exactly or estimated from `samples` random draws.
+#[derive(Debug)]
+struct Outcomes {
+    probabilities: BTreeMap<Pattern, f64>,
+    samples: Option<usize>,
+}
+
+impl Outcomes {
+    /// Above this many distinct draws, `Outcomes::new` samples instead of enumerating.
+    const EXACT_LIMIT: f64 = 1_000_000.0;
+    const SAMPLES: usize = 200_000;
+
+    /// Deals `unknown` more cards to the `known` ones from a deck holding `copies` of
+    /// every card in the rules' order, less the known cards. Draws are enumerated as
+    /// multisets, each weighted by how many ways the deck can produce it, unless there
+    /// are too many, in which case the deck is shuffled `SAMPLES` times with `rng` instead.
+    fn new(
+        known: &[char],
+        unknown: usize,
+        rules: &Rules,
+        copies: usize,
+        rng: &mut impl Rng,
+    ) -> Result<Self, HandError> {
+        if let Some(&card) = known.iter().find(|card| !rules.order.contains(card)) {
+            return Err(HandError::NotInDeck { card });
+        }
+
+        let deck = rules
+            .order
+            .iter()
+            .map(|&card| {
+                let held = known.iter().filter(|&&k| k == card).count();
+                copies
+                    .checked_sub(held)
+                    .map(|left| (card, left))
+                    .ok_or(HandError::TooManyCopies { card, copies })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let size = deck.iter().map(|&(_, left)| left).sum::<usize>();
+        if unknown > size {
+            return Err(HandError::DeckTooSmall { unknown, size });
+        }
+
+        // multisets of `unknown` cards from `deck.len()` labels, ignoring how many are left
+        let draws = (1..deck.len()).fold(1.0, |total, k| total * (unknown + k) as f64 / k as f64);
+
+        Ok(if draws <= Self::EXACT_LIMIT {
+            Self::enumerate(known, unknown, rules, &deck)
+        } else {
+            Self::sample(known, unknown, rules, &deck, rng)
         })
-        .sorted_unstable_by(|left, right| {
-            // compare elements
-            // Vec ord is element by element
-            match left.counts.cmp(&right.counts) {
-                std::cmp::Ordering::Equal => {
-                    // compare elements
-                    // Vec ord is element by element
-                    left.cards.cmp(&right.cards)
+    }
+
+    fn enumerate(known: &[char], unknown: usize, rules: &Rules, deck: &[(char, usize)]) -> Self {
+        fn deal(
+            deck: &[(char, usize)],
+            unknown: usize,
+            weight: f64,
+            cards: &mut Vec<char>,
+            rules: &Rules,
+            out: &mut BTreeMap<Pattern, f64>,
+        ) {
+            let Some((&(card, left), rest)) = deck.split_first() else {
+                if unknown == 0 {
+                    let hand = Hand {
+                        cards: cards.clone(),
+                        bid: 0,
+                    };
+                    *out.entry(hand.pattern(rules)).or_default() += weight;
                 }
-                o => o,
+                return;
+            };
+
+            for taken in 0..=left.min(unknown) {
+                cards.extend(std::iter::repeat_n(card, taken));
+                let ways = binomial(left as u64, taken as u64) as f64;
+                deal(rest, unknown - taken, weight * ways, cards, rules, out);
+                cards.truncate(cards.len() - taken);
             }
-        })
-        .enumerate()
-        .map(|(rank, hand)| (rank as u32 + 1) * hand.bid as u32)
-        .sum::<u32>()
+        }
+
+        let mut probabilities = BTreeMap::new();
+        deal(
+            deck,
+            unknown,
+            1.0,
+            &mut known.to_vec(),
+            rules,
+            &mut probabilities,
+        );
+
+        let total = probabilities.values().sum::<f64>();
+        probabilities.values_mut().for_each(|p| *p /= total);
+
+        Outcomes {
+            probabilities,
+            samples: None,
+        }
+    }
+
+    fn sample(
+        known: &[char],
+        unknown: usize,
+        rules: &Rules,
+        deck: &[(char, usize)],
+        rng: &mut impl Rng,
+    ) -> Self {
+        let mut cards = deck
+            .iter()
+            .flat_map(|&(card, left)| std::iter::repeat_n(card, left))
+            .collect_vec();
+
+        let mut probabilities = BTreeMap::new();
+        for _ in 0..Self::SAMPLES {
+            let (drawn, _) = cards.partial_shuffle(rng, unknown);
+            let hand = Hand {
+                cards: known.iter().chain(drawn.iter()).copied().collect(),
+                bid: 0,
+            };
+            *probabilities.entry(hand.pattern(rules)).or_default() += 1.0;
+        }
+        probabilities
+            .values_mut()
+            .for_each(|p| *p /= Self::SAMPLES as f64);
+
+        Outcomes {
+            probabilities,
+            samples: Some(Self::SAMPLES),
+        }
+    }
+}
+
+impl fmt::Display for Outcomes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.samples {
+            Some(samples) => writeln!(f, "estimated from {samples} samples")?,
+            None => writeln!(f, "exact")?,
+        }
+        for (pattern, probability) in self.probabilities.iter().rev() {
+            writeln!(
+                f,
+                "{:<14} {:>9.5}%",
+                pattern.to_string(),
+                probability * 100.0
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[aoc(day7, part1)]
+fn solve_part1(input: &Input) -> Result<u32, HandError> {
+    total_winnings(input, &Rules::standard())
+}
+
+#[aoc(day7, part2)]
+fn solve_part2(input: &Input) -> Result<u32, HandError> {
+    total_winnings(input, &Rules::jokers())
+}
+
+#[aoc(day7, part1, AcesLow)]
+fn solve_part1_aces_low(input: &Input) -> Result<u32, HandError> {
+    total_winnings(input, &Rules::aces_low())
+}
+
+#[aoc(day7, part2, QueensWild)]
+fn solve_part2_queens_wild(input: &Input) -> Result<u32, HandError> {
+    total_winnings(input, &Rules::standard().with_wild("JQ", WildRank::Lowest))
+}
+
+#[aoc(day7, part1, Table)]
+fn solve_part1_table(input: &Input) -> Result<u32, HandError> {
+    print!(
+        "{}",
+        ranking_table(input, &Rules::standard(), Table::Plain)?
+    );
+    solve_part1(input)
+}
+
+#[aoc(day7, part2, Table)]
+fn solve_part2_table(input: &Input) -> Result<u32, HandError> {
+    print!("{}", ranking_table(input, &Rules::jokers(), Table::Plain)?);
+    solve_part2(input)
+}
+
+#[aoc(day7, part1, Csv)]
+fn solve_part1_csv(input: &Input) -> Result<u32, HandError> {
+    print!("{}", ranking_table(input, &Rules::standard(), Table::Csv)?);
+    solve_part1(input)
+}
+
+#[aoc(day7, part2, Csv)]
+fn solve_part2_csv(input: &Input) -> Result<u32, HandError> {
+    print!("{}", ranking_table(input, &Rules::jokers(), Table::Csv)?);
+    solve_part2(input)
+}
+
+/// Odds of each hand type for a fresh deal from a four-suit deck, before solving.
+#[aoc(day7, part1, Odds)]
+fn solve_part1_odds(input: &Input) -> Result<u32, HandError> {
+    let size = input.first().map_or(5, |hand| hand.cards.len());
+    let odds = Outcomes::new(&[], size, &Rules::standard(), 4, &mut rand::thread_rng())?;
+    print!("{odds}");
+    solve_part1(input)
+}
+
+#[aoc(day7, part2, Odds)]
+fn solve_part2_odds(input: &Input) -> Result<u32, HandError> {
+    let size = input.first().map_or(5, |hand| hand.cards.len());
+    let odds = Outcomes::new(&[], size, &Rules::jokers(), 4, &mut rand::thread_rng())?;
+    print!("{odds}");
+    solve_part2(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn hand(cards: &str) -> Hand {
+        Hand {
+            cards: cards.chars().collect(),
+            bid: 0,
+        }
+    }
+
+    #[test]
+    fn classifies_every_type() {
+        let rules = Rules::standard();
+        for (cards, hand_type) in [
+            ("AAAAA", HandType::FiveOfAKind),
+            ("AA8AA", HandType::FourOfAKind),
+            ("23332", HandType::FullHouse),
+            ("TTT98", HandType::ThreeOfAKind),
+            ("23432", HandType::TwoPair),
+            ("A23A4", HandType::OnePair),
+            ("23456", HandType::HighCard),
+        ] {
+            assert_eq!(hand(cards).classify(&rules), Some(hand_type), "{cards}");
+        }
+    }
+
+    #[test]
+    fn jokers_join_the_biggest_group() {
+        let rules = Rules::jokers();
+        for (cards, hand_type) in [
+            ("JJJJJ", HandType::FiveOfAKind),
+            ("QJJQ2", HandType::FourOfAKind),
+            ("T55J5", HandType::FourOfAKind),
+            ("2233J", HandType::FullHouse),
+            ("J2345", HandType::OnePair),
+            ("JJ234", HandType::ThreeOfAKind),
+        ] {
+            assert_eq!(hand(cards).classify(&rules), Some(hand_type), "{cards}");
+        }
+    }
+
+    #[test]
+    fn hand_types_are_ordered() {
+        assert!(HandType::HighCard < HandType::OnePair);
+        assert!(HandType::TwoPair < HandType::ThreeOfAKind);
+        assert!(HandType::FullHouse < HandType::FourOfAKind);
+        assert!(HandType::FourOfAKind < HandType::FiveOfAKind);
+    }
+
+    #[test]
+    fn hands_order_by_type_then_cards() {
+        let rules = Rules::standard();
+        assert!(hand("2AAAA").rank(&rules) < hand("33332").rank(&rules));
+        assert!(hand("KTJJT").rank(&rules) < hand("KK677").rank(&rules));
+        assert!(hand("AAAAK").rank(&rules) < hand("22222").rank(&rules));
+        assert!(hand("23456").rank(&rules) == hand("23456").rank(&rules));
+
+        let rules = Rules::jokers();
+        assert!(hand("KK677").rank(&rules) < hand("KTJJT").rank(&rules));
+        assert!(hand("JKKK2").rank(&rules) < hand("QQQQ2").rank(&rules));
+    }
+
+    #[test]
+    fn example() {
+        let input = day7_generator("32T3K 765\nT55J5 684\nKK677 28\nKTJJT 220\nQQQJA 483").unwrap();
+        assert_eq!(solve_part1(&input).unwrap(), 6440);
+        assert_eq!(solve_part2(&input).unwrap(), 5905);
+    }
+
+    #[test]
+    fn rules_set_the_card_order() {
+        let rules = Rules::aces_low();
+        assert!(hand("A2345").rank(&rules) < hand("23456").rank(&rules));
+        assert!(hand("AAAKK").rank(&rules) < hand("KKKAA").rank(&rules));
+        assert_eq!(hand("AAKKK").classify(&rules), Some(HandType::FullHouse));
+    }
+
+    #[test]
+    fn several_wild_cards() {
+        let rules = Rules::standard().with_wild("JQ", WildRank::Lowest);
+        assert_eq!(hand("QJ2A3").classify(&rules), Some(HandType::ThreeOfAKind));
+        assert_eq!(rules.strength('J'), 0);
+        assert_eq!(rules.strength('Q'), 1);
+        assert_eq!(rules.strength('2'), 2);
+        assert!(hand("Q2222").rank(&rules) > hand("J2222").rank(&rules));
+        assert!(hand("Q2222").rank(&rules) < hand("22222").rank(&rules));
+
+        let rules = Rules::standard().with_wild("JQ", WildRank::Natural);
+        assert_eq!(rules.strength('Q'), Rules::standard().strength('Q'));
+        assert!(hand("Q2222").rank(&rules) > hand("22222").rank(&rules));
+    }
+
+    fn pattern(cards: &str, rules: &Rules) -> Vec<usize> {
+        hand(cards).pattern(rules).0
+    }
+
+    #[test]
+    fn three_card_hands() {
+        let rules = Rules::jokers();
+        assert_eq!(pattern("KKK", &rules), [3]);
+        assert_eq!(pattern("KJ2", &rules), [2, 1]);
+        assert_eq!(pattern("K92", &rules), [1, 1, 1]);
+        assert_eq!(pattern("JJJ", &rules), [3]);
+        assert_eq!(hand("KKK").classify(&rules), None);
+        assert!(hand("AK2").rank(&rules) < hand("223").rank(&rules));
+        assert!(hand("22A").rank(&rules) < hand("J22").rank(&rules));
+
+        let input = day7_generator("32T 5\nKKK 2\nJ2J 3\n9A9 7").unwrap();
+        assert_eq!(solve_part1(&input).unwrap(), 5 + 7 * 2 + 3 * 3 + 2 * 4);
+        // with jokers, J2J is three of a kind and the weakest of those
+        assert_eq!(solve_part2(&input).unwrap(), 5 + 7 * 2 + 3 * 3 + 2 * 4);
+    }
+
+    #[test]
+    fn five_card_hands() {
+        let rules = Rules::standard();
+        assert_eq!(pattern("32T3K", &rules), [2, 1, 1, 1]);
+        assert_eq!(pattern("KTJJT", &rules), [2, 2, 1]);
+        assert_eq!(pattern("QQQJA", &rules), [3, 1, 1]);
+        assert_eq!(pattern("QQQJA", &Rules::jokers()), [4, 1]);
+    }
+
+    #[test]
+    fn seven_card_hands() {
+        let rules = Rules::standard();
+        assert_eq!(pattern("3333222", &rules), [4, 3]);
+        assert_eq!(pattern("KKKKQQA", &rules), [4, 2, 1]);
+        assert_eq!(pattern("2345678", &rules), [1; 7]);
+        assert_eq!(pattern("2J3J4J5", &Rules::jokers()), [4, 1, 1, 1]);
+        assert_eq!(hand("3333222").pattern(&rules).to_string(), "4+3");
+        assert!(hand("KKKKQQA").rank(&rules) < hand("3333222").rank(&rules));
+        assert!(hand("AAAKKQQ").rank(&rules) < hand("2222345").rank(&rules));
+        assert!(hand("AAKKQQ2").rank(&rules) < hand("222KK34").rank(&rules));
+
+        let input = day7_generator("3333222 5\nKKKKQQA 2\nJJJJJJJ 3\n2345678 7").unwrap();
+        assert_eq!(solve_part1(&input).unwrap(), 7 + 2 * 2 + 5 * 3 + 3 * 4);
+    }
+
+    #[test]
+    fn hands_must_be_the_same_size() {
+        assert!(matches!(
+            day7_generator("32T3K 765\nT55J 684"),
+            Err(HandError::MixedSizes {
+                line: 2,
+                expected: 5,
+                found: 4
+            })
+        ));
+    }
+
+    #[test]
+    fn cards_outside_the_deck_are_rejected() {
+        let input = day7_generator("32T3K 765\nT55X5 684").unwrap();
+        assert!(matches!(
+            solve_part1(&input),
+            Err(HandError::UnknownCard { card: 'X', line: 2 })
+        ));
+        assert!(ranking_table(&input, &Rules::jokers(), Table::Csv).is_err());
+    }
+
+    const EXAMPLE: &str = "32T3K 765\nT55J5 684\nKK677 28\nKTJJT 220\nQQQJA 483";
+
+    #[test]
+    fn ranking_tables_show_the_played_hand() {
+        let input = day7_generator(EXAMPLE).unwrap();
+        assert_eq!(
+            ranking_table(&input, &Rules::jokers(), Table::Plain).unwrap(),
+            "  rank  hand    played  type              bid   winnings
+     1  32T3K   32T3K   OnePair           765        765
+     2  KK677   KK677   TwoPair            28         56
+     3  T55J5   T5555   FourOfAKind       684       2052
+     4  QQQJA   QQQQA   FourOfAKind       483       1932
+     5  KTJJT   KTTTT   FourOfAKind       220       1100
+"
+        );
+        assert_eq!(
+            ranking_table(&input, &Rules::jokers(), Table::Csv).unwrap(),
+            "rank,hand,played,type,bid,winnings
+1,32T3K,32T3K,OnePair,765,765
+2,KK677,KK677,TwoPair,28,56
+3,T55J5,T5555,FourOfAKind,684,2052
+4,QQQJA,QQQQA,FourOfAKind,483,1932
+5,KTJJT,KTTTT,FourOfAKind,220,1100
+"
+        );
+    }
+
+    #[test]
+    fn all_wild_hands_play_the_strongest_card() {
+        assert_eq!(hand("JJJJJ").played(&Rules::jokers()), "AAAAA");
+        let rules = Rules::standard().with_wild("AJ", WildRank::Lowest);
+        assert_eq!(hand("JAJAJ").played(&rules), "KKKKK");
+    }
+
+    fn odds(outcomes: &Outcomes, counts: &[usize]) -> f64 {
+        outcomes
+            .probabilities
+            .get(&Pattern(counts.to_vec()))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn exact_odds() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let fresh = Outcomes::new(&[], 5, &Rules::standard(), 4, &mut rng).unwrap();
+        assert_eq!(fresh.samples, None);
+        assert!((odds(&fresh, &[2, 1, 1, 1]) - 0.422569).abs() < 1e-6);
+        assert!((odds(&fresh, &[1, 1, 1, 1, 1]) - 0.507083).abs() < 1e-6);
+        assert!((fresh.probabilities.values().sum::<f64>() - 1.0).abs() < 1e-9);
+
+        let pair = Outcomes::new(&['A', 'A'], 3, &Rules::standard(), 4, &mut rng).unwrap();
+        assert_eq!(odds(&pair, &[1, 1, 1, 1, 1]), 0.0);
+        assert!(odds(&pair, &[2, 1, 1, 1]) > 0.0);
+    }
+
+    #[test]
+    fn sampled_odds_are_close_to_exact() {
+        let rules = Rules::standard();
+        let deck = rules.order.iter().map(|&card| (card, 4)).collect_vec();
+        let exact = Outcomes::enumerate(&[], 5, &rules, &deck);
+
+        let sampled = Outcomes::sample(&[], 5, &rules, &deck, &mut StdRng::seed_from_u64(2023));
+        assert_eq!(sampled.samples, Some(Outcomes::SAMPLES));
+        for (pattern, probability) in &exact.probabilities {
+            let estimate = sampled
+                .probabilities
+                .get(pattern)
+                .copied()
+                .unwrap_or_default();
+            assert!((estimate - probability).abs() < 0.005, "{pattern}");
+        }
+
+        // the same seed deals the same cards
+        let again = Outcomes::sample(&[], 5, &rules, &deck, &mut StdRng::seed_from_u64(2023));
+        assert_eq!(again.probabilities, sampled.probabilities);
+    }
+
+    #[test]
+    fn impossible_deals_are_rejected() {
+        let rules = Rules::standard();
+        let mut rng = StdRng::seed_from_u64(7);
+        assert!(matches!(
+            Outcomes::new(&['X'], 4, &rules, 4, &mut rng),
+            Err(HandError::NotInDeck { card: 'X' })
+        ));
+        assert!(matches!(
+            Outcomes::new(&['A'; 5], 0, &rules, 4, &mut rng),
+            Err(HandError::TooManyCopies {
+                card: 'A',
+                copies: 4
+            })
+        ));
+        assert!(matches!(
+            Outcomes::new(&['A'], 52, &rules, 4, &mut rng),
+            Err(HandError::DeckTooSmall {
+                unknown: 52,
+                size: 51
+            })
+        ));
+    }
 }
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt};
 
 use aoc_runner_derive::{aoc, aoc_generator};
 
@@ -18,15 +18,118 @@ enum Turn {
     Right,
 }
 
+/// The map with every node label interned to its index in `labels`;
+/// `edges[n]` holds the left and right neighbours of node `n`.
 #[derive(Debug)]
 struct Directions {
     steps: Vec<Turn>,
-    nodes: HashMap<String, (String, String)>,
+    labels: Vec<String>,
+    edges: Vec<(usize, usize)>,
+}
+
+#[derive(Debug)]
+pub enum NetworkError {
+    Duplicate {
+        label: String,
+        line: usize,
+        first_line: usize,
+    },
+    Undefined {
+        label: String,
+        line: usize,
+    },
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkError::Duplicate {
+                label,
+                line,
+                first_line,
+            } => write!(
+                f,
+                "line {line}: node {label} is defined again (first defined on line {first_line})"
+            ),
+            NetworkError::Undefined { label, line } => {
+                write!(f, "line {line}: node {label} is never defined")
+            }
+        }
+    }
+}
+
+impl std::error::Error for NetworkError {}
+
+impl Directions {
+    /// Node definitions start on line 3, after the steps and a blank line.
+    const FIRST_NODE_LINE: usize = 3;
+
+    fn new(steps: Vec<Turn>, nodes: Vec<Node>) -> Result<Self, NetworkError> {
+        let mut ids = HashMap::with_capacity(nodes.len());
+        for (n, &(label, _)) in nodes.iter().enumerate() {
+            if let Some(first) = ids.insert(label, n) {
+                return Err(NetworkError::Duplicate {
+                    label: label.to_string(),
+                    line: n + Self::FIRST_NODE_LINE,
+                    first_line: first + Self::FIRST_NODE_LINE,
+                });
+            }
+        }
+
+        let id = |label: &str, n: usize| {
+            ids.get(label)
+                .copied()
+                .ok_or_else(|| NetworkError::Undefined {
+                    label: label.to_string(),
+                    line: n + Self::FIRST_NODE_LINE,
+                })
+        };
+        let edges = nodes
+            .iter()
+            .enumerate()
+            .map(|(n, &(_, (left, right)))| Ok((id(left, n)?, id(right, n)?)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Directions {
+            steps,
+            labels: nodes
+                .into_iter()
+                .map(|(label, _)| label.to_string())
+                .collect(),
+            edges,
+        })
+    }
+
+    fn id(&self, label: &str) -> Option<usize> {
+        self.labels.iter().position(|l| l == label)
+    }
+
+    /// Steps taken from `start` until reaching a node for which `is_end` holds.
+    fn walk(&self, start: usize, is_end: impl Fn(usize) -> bool) -> u64 {
+        let mut node = start;
+        let mut count = 0;
+
+        for step in self.steps.iter().cycle() {
+            if is_end(node) {
+                break;
+            }
+            count += 1;
+            let (left, right) = self.edges[node];
+            node = match step {
+                Turn::Left => left,
+                Turn::Right => right,
+            };
+        }
+        count
+    }
 }
 
 type Input = Directions;
 
-fn parse_input(input: &str) -> IResult<&str, Input> {
+/// A node's label and its left and right neighbours, as written.
+type Node<'a> = (&'a str, (&'a str, &'a str));
+
+fn parse_input(input: &str) -> IResult<&str, (Vec<Turn>, Vec<Node<'_>>)> {
     let (input, steps) = terminated(
         many1(alt((
             tag("L").map(|_| Turn::Left),
@@ -40,34 +143,22 @@ fn parse_input(input: &str) -> IResult<&str, Input> {
         separated_list1(
             line_ending,
             separated_pair(
-                take(3_usize).map(String::from),
+                take(3_usize),
                 tag(" = "),
                 delimited(
                     tag("("),
-                    separated_pair(
-                        take(3_usize).map(String::from),
-                        tag(", "),
-                        take(3_usize).map(String::from),
-                    ),
+                    separated_pair(take(3_usize), tag(", "), take(3_usize)),
                     tag(")"),
                 ),
             ),
         ),
     )(input)?;
 
-    Ok((
-        input,
-        Directions {
-            steps,
-            nodes: nodes
-                .into_iter()
-                .collect::<HashMap<String, (String, String)>>(),
-        },
-    ))
+    Ok((input, (steps, nodes)))
 }
 
 #[aoc_generator(day8)]
-fn day8_generator(input: &str) -> Input {
+fn day8_generator(input: &str) -> Result<Input, NetworkError> {
     let _input = "RL
 
 AAA = (BBB, CCC)
@@ -95,56 +186,93 @@ ZZZ = (ZZZ, ZZZ)";
 22Z = (22B, 22B)
 XXX = (XXX, XXX)";
 
-    let (input, output) = parse_input(input).expect("Could not parse input");
+    let (input, (steps, nodes)) = parse_input(input).expect("Could not parse input");
     assert!(input.is_empty());
-    output
+    Directions::new(steps, nodes)
 }
 
 #[aoc(day8, part1)]
-fn solve_part1(input: &Input) -> u32 {
-    let mut steps = input.steps.iter().cycle();
-
-    let mut curr = String::from("AAA");
-    let mut count = 0;
-
-    while curr != "ZZZ" {
-        count += 1;
-        let Some(step) = steps.next() else {
-            panic!("Cycling - can't run out of steps!")
-        };
-        let (left, right) = input.nodes.get(&curr).expect("Direction doesn't exist");
-        match step {
-            Turn::Left => curr = left.to_string(),
-            Turn::Right => curr = right.to_string(),
-        }
-    }
-    count
+fn solve_part1(input: &Input) -> u64 {
+    let start = input.id("AAA").expect("There is no AAA node");
+    let end = input.id("ZZZ").expect("There is no ZZZ node");
+    input.walk(start, |node| node == end)
 }
 
 #[aoc(day8, part2)]
 fn solve_part2(input: &Input) -> u64 {
-    let mut instructions = input.steps.iter().cycle();
+    let is_end = input
+        .labels
+        .iter()
+        .map(|label| label.ends_with('Z'))
+        .collect::<Vec<_>>();
+
     input
-        .nodes
-        .keys()
-        .filter(|k| k.ends_with('A'))
-        .map(|mut v| {
-            let mut count = 0;
-            while !v.ends_with('Z') {
-                count += 1;
-                let Some(step) = instructions.next() else {
-                    panic!("Cycling - can't run out");
-                };
-
-                let (left, right) = input.nodes.get(v).expect("Direction doesn't exist");
-
-                v = match step {
-                    Turn::Left => left,
-                    Turn::Right => right,
-                }
-            }
-            count as u64
-        })
+        .labels
+        .iter()
+        .enumerate()
+        .filter(|(_, label)| label.ends_with('A'))
+        .map(|(start, _)| input.walk(start, |node| is_end[node]))
         .reduce(|acc, e| acc.lcm(&e))
         .expect("Not able to compute lcm")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn examples() {
+        let first = "RL
+
+AAA = (BBB, CCC)
+BBB = (DDD, EEE)
+CCC = (ZZZ, GGG)
+DDD = (DDD, DDD)
+EEE = (EEE, EEE)
+GGG = (GGG, GGG)
+ZZZ = (ZZZ, ZZZ)";
+        assert_eq!(solve_part1(&day8_generator(first).unwrap()), 2);
+
+        let repeating = "LLR
+
+AAA = (BBB, BBB)
+BBB = (AAA, ZZZ)
+ZZZ = (ZZZ, ZZZ)";
+        assert_eq!(solve_part1(&day8_generator(repeating).unwrap()), 6);
+
+        let ghosts = "LR
+
+11A = (11B, XXX)
+11B = (XXX, 11Z)
+11Z = (11B, XXX)
+22A = (22B, XXX)
+22B = (22C, 22C)
+22C = (22Z, 22Z)
+22Z = (22B, 22B)
+XXX = (XXX, XXX)";
+        assert_eq!(solve_part2(&day8_generator(ghosts).unwrap()), 6);
+    }
+
+    #[test]
+    fn nodes_are_defined_exactly_once() {
+        let duplicate = "LR
+
+AAA = (BBB, BBB)
+BBB = (AAA, ZZZ)
+AAA = (ZZZ, ZZZ)
+ZZZ = (ZZZ, ZZZ)";
+        assert!(matches!(
+            day8_generator(duplicate),
+            Err(NetworkError::Duplicate { label, line: 5, first_line: 3 }) if label == "AAA"
+        ));
+
+        let undefined = "LR
+
+AAA = (BBB, ZZZ)
+ZZZ = (ZZZ, ZZZ)";
+        assert!(matches!(
+            day8_generator(undefined),
+            Err(NetworkError::Undefined { label, line: 3 }) if label == "BBB"
+        ));
+    }
+}
@@ -0,0 +1,236 @@
+use num::{PrimInt, Unsigned};
+use std::ops::RangeInclusive;
+
+/// A set of integers stored as sorted, disjoint, non-touching inclusive ranges.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntervalSet<T> {
+    intervals: Vec<RangeInclusive<T>>,
+}
+
+impl<T: PrimInt + Unsigned> IntervalSet<T> {
+    /// Sorts the ranges, drops empty ones and merges any that overlap or touch.
+    fn normalize(mut intervals: Vec<RangeInclusive<T>>) -> Self {
+        intervals.retain(|r| r.start() <= r.end());
+        intervals.sort_unstable_by_key(|r| *r.start());
+
+        let mut merged: Vec<RangeInclusive<T>> = Vec::with_capacity(intervals.len());
+        for interval in intervals {
+            match merged.last_mut() {
+                Some(previous)
+                    if previous
+                        .end()
+                        .checked_add(&T::one())
+                        .is_none_or(|next| *interval.start() <= next) =>
+                {
+                    if interval.end() > previous.end() {
+                        *previous = *previous.start()..=*interval.end();
+                    }
+                }
+                _ => merged.push(interval),
+            }
+        }
+
+        IntervalSet { intervals: merged }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &RangeInclusive<T>> {
+        self.intervals.iter()
+    }
+
+    pub fn min(&self) -> Option<T> {
+        self.intervals.first().map(|r| *r.start())
+    }
+
+    pub fn max(&self) -> Option<T> {
+        self.intervals.last().map(|r| *r.end())
+    }
+
+    /// How many values are in the set, or `None` if that doesn't fit in `T`,
+    /// which only happens when the set is the whole of `T`.
+    pub fn count(&self) -> Option<T> {
+        self.iter().try_fold(T::zero(), |total, r| {
+            total
+                .checked_add(&(*r.end() - *r.start()))?
+                .checked_add(&T::one())
+        })
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        self.contains_range(value..=value)
+    }
+
+    pub fn contains_range(&self, range: RangeInclusive<T>) -> bool {
+        let at = self
+            .intervals
+            .partition_point(|r| r.start() <= range.start());
+        at > 0 && self.intervals[at - 1].end() >= range.end()
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        Self::normalize(self.iter().chain(other.iter()).cloned().collect())
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut intervals = vec![];
+        let (mut left, mut right) = (self.iter().peekable(), other.iter().peekable());
+
+        while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+            let start = *l.start().max(r.start());
+            let end = *l.end().min(r.end());
+            if start <= end {
+                intervals.push(start..=end);
+            }
+            // whichever ends first can't overlap anything further along
+            if l.end() < r.end() {
+                left.next();
+            } else {
+                right.next();
+            }
+        }
+
+        IntervalSet { intervals }
+    }
+
+    /// Everything within `bounds` that isn't in the set.
+    pub fn complement(&self, bounds: RangeInclusive<T>) -> Self {
+        let (low, high) = bounds.into_inner();
+        let mut intervals = vec![];
+        let mut next = Some(low);
+
+        for interval in &self.intervals {
+            let Some(start) = next else {
+                break;
+            };
+            if *interval.start() > start {
+                intervals.push(start..=(*interval.start() - T::one()).min(high));
+            }
+            next = interval.end().checked_add(&T::one()).map(|n| n.max(start));
+        }
+        if let Some(start) = next {
+            intervals.push(start..=high);
+        }
+
+        intervals.retain(|r| r.start() <= r.end());
+        IntervalSet { intervals }
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        self.intersection(&other.complement(T::zero()..=T::max_value()))
+    }
+}
+
+impl<T> Default for IntervalSet<T> {
+    fn default() -> Self {
+        IntervalSet { intervals: vec![] }
+    }
+}
+
+impl<T: PrimInt + Unsigned> From<RangeInclusive<T>> for IntervalSet<T> {
+    fn from(range: RangeInclusive<T>) -> Self {
+        Self::normalize(vec![range])
+    }
+}
+
+impl<T: PrimInt + Unsigned> FromIterator<RangeInclusive<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = RangeInclusive<T>>>(iter: I) -> Self {
+        Self::normalize(iter.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::collections::BTreeSet;
+
+    fn random_set(rng: &mut StdRng) -> (IntervalSet<u8>, BTreeSet<u8>) {
+        let ranges = (0..rng.gen_range(0..5))
+            .map(|_| {
+                let start = rng.gen::<u8>();
+                start..=start.saturating_add(rng.gen_range(0..40))
+            })
+            .collect::<Vec<_>>();
+        let reference = ranges.iter().flat_map(|r| r.clone()).collect();
+        (ranges.into_iter().collect(), reference)
+    }
+
+    fn values(set: &IntervalSet<u8>) -> BTreeSet<u8> {
+        set.iter().flat_map(|r| r.clone()).collect()
+    }
+
+    fn is_normalized(set: &IntervalSet<u8>) -> bool {
+        set.iter().all(|r| r.start() <= r.end())
+            && set
+                .iter()
+                .zip(set.iter().skip(1))
+                .all(|(a, b)| (*a.end() as u16) + 1 < *b.start() as u16)
+    }
+
+    #[test]
+    fn matches_btreeset() {
+        let mut rng = StdRng::seed_from_u64(2023);
+        for _ in 0..2000 {
+            let (a, a_ref) = random_set(&mut rng);
+            let (b, b_ref) = random_set(&mut rng);
+            assert!(is_normalized(&a));
+            assert_eq!(values(&a), a_ref);
+
+            let union = a.union(&b);
+            assert!(is_normalized(&union));
+            assert_eq!(values(&union), &a_ref | &b_ref);
+
+            let intersection = a.intersection(&b);
+            assert!(is_normalized(&intersection));
+            assert_eq!(values(&intersection), &a_ref & &b_ref);
+
+            let difference = a.difference(&b);
+            assert!(is_normalized(&difference));
+            assert_eq!(values(&difference), &a_ref - &b_ref);
+
+            let low = rng.gen::<u8>();
+            let high = rng.gen_range(low..=u8::MAX);
+            let complement = a.complement(low..=high);
+            assert!(is_normalized(&complement));
+            assert_eq!(
+                values(&complement),
+                (low..=high).filter(|v| !a_ref.contains(v)).collect()
+            );
+
+            for value in 0..=u8::MAX {
+                assert_eq!(a.contains(value), a_ref.contains(&value));
+            }
+            let start = rng.gen::<u8>();
+            let end = start.saturating_add(rng.gen_range(0..20));
+            assert_eq!(
+                a.contains_range(start..=end),
+                (start..=end).all(|v| a_ref.contains(&v))
+            );
+
+            assert_eq!(a.count(), Some(a_ref.len() as u8));
+            assert_eq!(a.min(), a_ref.first().copied());
+            assert_eq!(a.max(), a_ref.last().copied());
+        }
+    }
+
+    #[test]
+    fn count_of_the_whole_domain() {
+        assert_eq!(IntervalSet::from(0..=u8::MAX).count(), None);
+        assert_eq!(IntervalSet::from(0..=u8::MAX - 1).count(), Some(u8::MAX));
+        assert_eq!(IntervalSet::from(0..=u64::MAX).count(), None);
+        assert_eq!(IntervalSet::<u64>::default().count(), Some(0));
+    }
+
+    #[test]
+    fn complement_at_the_edges() {
+        let set = IntervalSet::from_iter([0..=3_u8, 250..=u8::MAX]);
+        assert_eq!(set.complement(0..=u8::MAX), IntervalSet::from(4..=249));
+        assert_eq!(
+            IntervalSet::default().complement(0..=u8::MAX),
+            IntervalSet::from(0..=u8::MAX)
+        );
+        assert_eq!(
+            IntervalSet::from(0..=u8::MAX).complement(0..=u8::MAX),
+            IntervalSet::default()
+        );
+    }
+}
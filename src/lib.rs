@@ -16,5 +16,6 @@ mod day_11;
 mod day_12;
 mod day_13;
 mod day_15;
+mod interval_set;
 
 aoc_lib! { year = 2023 }